@@ -0,0 +1,35 @@
+//! Compares `Buffer`'s numeric writers against `write!`-based formatting.
+//!
+//! Run with `cargo bench --bench numeric_format`.
+
+use core::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use noir_macros_core::Buffer;
+
+const ITERATIONS: u32 = 200_000;
+
+fn bench(name: &str, mut body: impl FnMut(&mut Buffer)) {
+    let mut buffer = Buffer::with_capacity(64);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        *buffer.pos.get_mut() = 0;
+        body(black_box(&mut buffer));
+    }
+    let elapsed: Duration = start.elapsed();
+    println!("{:<24} {:>8.1} ns/iter", name, elapsed.as_nanos() as f64 / f64::from(ITERATIONS));
+}
+
+fn main() {
+    let unsigned = black_box(u64::MAX - 12_345);
+    let signed = black_box(i64::MIN + 6_789);
+    let float = black_box(-12_345.678_9_f64);
+
+    bench("write_u64", |b| b.write_u64(unsigned).unwrap());
+    bench("write! u64", |b| write!(b, "{}", unsigned).unwrap());
+    bench("write_i64", |b| b.write_i64(signed).unwrap());
+    bench("write! i64", |b| write!(b, "{}", signed).unwrap());
+    bench("write_f64 (3 places)", |b| b.write_f64(float, 3).unwrap());
+    bench("write! f64 (3 places)", |b| write!(b, "{:.3}", float).unwrap());
+}
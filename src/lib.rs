@@ -28,10 +28,16 @@
 //! noir_macros_core is distributed under the MIT License.
 
 extern crate alloc;
+#[cfg(any(test, feature = "std"))]
+extern crate std;
 
-use alloc::vec::Vec;
+/// The `Vec` built by [`vec!`], re-exported so callers need no `alloc` import.
+pub use alloc::vec::Vec;
+use core::any::Any;
 use core::cell::UnsafeCell;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
 
 /// A thread-safe static initialization cell.
 /// 
@@ -42,16 +48,35 @@ use core::sync::atomic::{AtomicBool, Ordering};
 /// # Memory Layout
 /// ```text
 /// StaticCell<T>: align(8) {
-///     initialized: AtomicBool,     // Thread-safe state tracking
+///     state: AtomicU8,             // Uninitialized, initializing or ready
+///     initializer: AtomicUsize,    // Context running `get_or_init`, if any
 ///     value: UnsafeCell<Option<T>> // Protected storage
 /// }
 /// ```
 /// 
+/// # Dropping
+/// A cell that goes out of scope drops its value, if it holds one, through
+/// the drop glue of its `Option<T>` storage. Cells in `static`s are never
+/// dropped, like any other static.
+///
 /// # Thread Safety
-/// The type implements `Sync` when `T: Sync` because:
+/// The type implements `Sync` when `T: Send + Sync` because:
 /// - Initialization is protected by atomic operations
 /// - The value becomes immutable after initialization
 /// - All access is properly synchronized
+///
+/// `T: Send` is required because a shared cell lets one thread store a
+/// value that other threads then use, and a value stored through
+/// [`try_init`](StaticCell::try_init) is dropped with the cell on whichever
+/// thread owns it. Types that must stay on their thread are rejected:
+///
+/// ```rust,compile_fail
+/// use noir_macros_core::StaticCell;
+/// use std::sync::MutexGuard;
+///
+/// // `MutexGuard` is `Sync` but not `Send`
+/// static GUARD: StaticCell<MutexGuard<'static, u32>> = StaticCell::new();
+/// ```
 /// 
 /// # Examples
 /// 
@@ -73,22 +98,301 @@ use core::sync::atomic::{AtomicBool, Ordering};
 /// ```
 #[repr(align(8))]
 pub struct StaticCell<T> {
-    initialized: AtomicBool,
+    state: AtomicU8,
+    initializer: AtomicUsize,
     value: UnsafeCell<Option<T>>,
 }
 
+// Cell states: a winning `set` moves from `UNINIT` to `INITIALIZING`, stores
+// the value, then publishes it by moving to `READY`
+const UNINIT: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+
 impl<T> StaticCell<T> {
     /// Creates a new uninitialized static cell.
     pub const fn new() -> Self {
         Self {
-            initialized: AtomicBool::new(false),
+            state: AtomicU8::new(UNINIT),
+            initializer: AtomicUsize::new(0),
             value: UnsafeCell::new(None),
         }
     }
 
+    /// Returns the contained value, initializing it with `f` if the cell is
+    /// empty.
+    ///
+    /// If `f` itself calls `get_or_init` on the same cell, the nested call
+    /// panics with "reentrant initialization" instead of recursing forever.
+    /// Detection relies on [`set_context_id_provider`] to tell execution
+    /// contexts apart. Without a provider, bare-metal targets
+    /// (`target_os = "none"`) assume a single execution context, and with
+    /// the `std` feature each thread is its own context.
+    ///
+    /// The value is returned as an [`Initialized`] handle, so code receiving
+    /// it knows the cell is initialized without checking again.
+    ///
+    /// # Contention
+    /// Once the cell is initialized, `f` is never called. While it is still
+    /// empty, threads racing on it may each call `f`: exactly one result is
+    /// stored and the others are dropped. A caller whose result lost waits
+    /// until the winner has published its value, so every caller gets a
+    /// reference to the stored value and never observes it half-written.
+    /// Keep `f` free of side effects that must happen only once, or guard
+    /// them separately.
+    ///
+    /// # Panics
+    /// Panics if the calling context re-enters initialization of this cell.
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> Initialized<'_, T> {
+        match self.get_or_try_init(|| Ok::<T, core::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns the contained value, initializing it with `f` if the cell is
+    /// empty and `f` succeeds.
+    ///
+    /// If `f` fails, its error is returned and the cell stays empty, so a
+    /// later call can retry. If another thread initializes the cell while
+    /// `f` runs, the value produced by `f` is dropped and the other
+    /// thread's value is returned, as for
+    /// [`get_or_init`](Self::get_or_init), whose reentrancy detection also
+    /// applies.
+    ///
+    /// # Panics
+    /// Panics if the calling context re-enters initialization of this cell.
+    pub fn get_or_try_init<E>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<Initialized<'_, T>, E> {
+        if let Some(value) = self.get() {
+            return Ok(Initialized(value));
+        }
+
+        // Context ids are stored off by one so that 0 means "no initializer"
+        let marker = current_context_id().map(|id| id.wrapping_add(1));
+        if let Some(marker) = marker {
+            if self.initializer.load(Ordering::Acquire) == marker {
+                panic!("reentrant initialization");
+            }
+        }
+        let owned = marker.is_some_and(|marker| {
+            self.initializer
+                .compare_exchange(0, marker, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        });
+        let _clear = Defer::new(|| {
+            if owned {
+                self.initializer.store(0, Ordering::Release);
+            }
+        });
+
+        self.try_init(f()?);
+        Ok(Initialized(self.wait()))
+    }
+
     /// Attempts to get a reference to the contained value.
     pub fn get(&self) -> Option<&T> {
-        if self.initialized.load(Ordering::Acquire) {
+        // SAFETY: `Acquire` synchronizes with the `Release` store of `READY`
+        unsafe { self.get_ordered(Ordering::Acquire) }
+    }
+
+    /// Returns a mutable reference to the contained value, if any.
+    ///
+    /// Taking `&mut self` proves no other reference to the cell exists, so
+    /// no atomics are needed. This is only possible while the cell is
+    /// uniquely owned, e.g. during single-threaded setup before it is
+    /// shared, or in tests.
+    #[inline]
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut().as_mut()
+    }
+
+    /// Moves the value out and returns the cell to its uninitialized state,
+    /// so a later `try_init` succeeds again.
+    ///
+    /// Like [`get_mut`](Self::get_mut), this needs unique access, which
+    /// makes it sound without synchronization.
+    pub fn take(&mut self) -> Option<T> {
+        *self.state.get_mut() = UNINIT;
+        self.value.get_mut().take()
+    }
+
+    /// Returns true if the cell holds a value.
+    ///
+    /// Cheaper than `get().is_some()` when the value itself is not needed.
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.state.load(Ordering::Acquire) == READY
+    }
+
+    /// Attempts to initialize the cell with a value.
+    ///
+    /// Use [`set`](Self::set) to get the value back if the cell was
+    /// already initialized.
+    pub fn try_init(&self, value: T) -> bool {
+        self.set(value).is_ok()
+    }
+
+    /// Initializes the cell with `value`, or hands `value` back as the error
+    /// if the cell was already initialized.
+    ///
+    /// Unlike [`try_init`](Self::try_init), losing the race does not lose
+    /// the value, which matters for values that are expensive to rebuild or
+    /// that own a resource.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        // SAFETY: `AcqRel` publishes the value to `Acquire` readers through a `Release` store
+        unsafe { self.set_ordered(value, Ordering::AcqRel, Ordering::Relaxed) }
+    }
+
+    /// Initializes the cell with `value`, or returns the value it already
+    /// holds.
+    ///
+    /// Returns `Ok(())` if this call initialized the cell. Otherwise `value`
+    /// is dropped and the existing value is returned as `Err`, so callers
+    /// can react to someone else having set it first.
+    pub fn init_or_get(&self, value: T) -> Result<(), &T> {
+        if self.try_init(value) {
+            Ok(())
+        } else {
+            Err(self.wait())
+        }
+    }
+
+    /// Returns the contained value, or `default` if the cell is uninitialized.
+    pub fn unwrap_or<'a>(&'a self, default: &'a T) -> &'a T {
+        self.get().unwrap_or(default)
+    }
+
+    /// Applies `f` to the contained value, or returns `default` if the cell
+    /// is uninitialized.
+    pub fn map_or<R>(&self, default: R, f: impl FnOnce(&T) -> R) -> R {
+        self.get().map_or(default, f)
+    }
+
+    /// Returns the contained value, initializing it with `f` if needed, using
+    /// only `Relaxed` atomics and no compare-exchange.
+    ///
+    /// This is a fast path for targets such as Cortex-M0, where ordered or
+    /// read-modify-write atomics are expensive or unavailable. The value is
+    /// stored before the cell is marked ready, separated by compiler fences,
+    /// so no hardware fence is emitted.
+    ///
+    /// # Safety
+    /// The caller must guarantee that no other access to this cell can race
+    /// with initialization, i.e. one of:
+    /// - the program runs on a single core and no interrupt handler or other
+    ///   execution context touches this cell while it is being initialized, or
+    /// - every access to this cell is externally synchronized (e.g. inside a
+    ///   critical section or behind a lock).
+    ///
+    /// In addition, `f` must not access this cell.
+    ///
+    /// # Panics
+    /// Panics if the cell is observed mid-initialization by a concurrent
+    /// [`try_init`](Self::try_init), which the contract above rules out.
+    pub unsafe fn get_or_init_racy(&self, f: impl FnOnce() -> T) -> &T {
+        use core::sync::atomic::compiler_fence;
+
+        match self.state.load(Ordering::Relaxed) {
+            READY => {}
+            UNINIT => {
+                let value = f();
+                // SAFETY: the caller guarantees no other access races with us
+                unsafe { *self.value.get() = Some(value) };
+                compiler_fence(Ordering::Release);
+                self.state.store(READY, Ordering::Relaxed);
+            }
+            _ => panic!("StaticCell accessed during a concurrent initialization"),
+        }
+        compiler_fence(Ordering::Acquire);
+        // SAFETY: the cell is ready, and the value is never modified after
+        // initialization
+        match unsafe { (*self.value.get()).as_ref() } {
+            Some(value) => value,
+            None => unreachable!(),
+        }
+    }
+
+    /// Drops the contained value and returns the cell to its uninitialized
+    /// state, so each test can start from a fresh static.
+    ///
+    /// Only available in this crate's tests or with the `test-utils` feature.
+    ///
+    /// # Safety
+    /// No other thread may access the cell during the reset, and no
+    /// reference previously returned by the cell may be used afterwards.
+    /// Since tests run concurrently by default, tests touching the same
+    /// static must be serialized (e.g. with `--test-threads=1` or a lock).
+    #[cfg(any(test, feature = "test-utils"))]
+    pub unsafe fn reset(&self) {
+        // SAFETY: the caller guarantees exclusive access during the reset
+        let old = unsafe { (*self.value.get()).take() };
+        self.state.store(UNINIT, Ordering::Release);
+        drop(old);
+    }
+
+    /// Spins until the value stored by a successful `try_init` is ready.
+    ///
+    /// Only call this once some `try_init` on the cell has returned, so the
+    /// wait is bounded by the initializing thread finishing its store. Only
+    /// the state is polled; the value is not read until it is published.
+    pub(crate) fn wait(&self) -> &T {
+        while self.state.load(Ordering::Acquire) != READY {
+            relax();
+        }
+        // SAFETY: `Acquire` synchronizes with the `Release` store of `READY`
+        match unsafe { (*self.value.get()).as_ref() } {
+            Some(value) => value,
+            None => unreachable!(),
+        }
+    }
+
+    /// Returns the contained value without checking that the cell is
+    /// initialized.
+    ///
+    /// Skips the atomic load and the branch of [`get`](Self::get), for hot
+    /// paths that run only after initialization is guaranteed.
+    ///
+    /// # Safety
+    /// A `try_init` or `set` on this cell must have completed, and its
+    /// effect must be visible to the calling thread: either it ran on this
+    /// thread, or this thread has since seen [`get`](Self::get) return
+    /// `Some` or [`is_initialized`](Self::is_initialized) return `true`, or
+    /// the value's publication reached it through another synchronizing
+    /// operation. Observing a `try_init` that is still in progress is not
+    /// enough.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use noir_macros_core::StaticCell;
+    ///
+    /// static SCALE: StaticCell<u32> = StaticCell::new();
+    ///
+    /// fn scaled(raw: u32) -> u32 {
+    ///     // SAFETY: `main` initializes `SCALE` before calling `scaled`
+    ///     raw * unsafe { SCALE.get_unchecked() }
+    /// }
+    ///
+    /// assert!(SCALE.try_init(4));
+    /// assert_eq!(scaled(10), 40);
+    /// ```
+    #[inline]
+    pub unsafe fn get_unchecked(&self) -> &T {
+        // SAFETY: the caller guarantees a completed and visible initialization
+        unsafe { (*self.value.get()).as_ref().unwrap_unchecked() }
+    }
+
+    /// Low-level variant of [`get`](Self::get) with an explicit load ordering.
+    ///
+    /// # Safety
+    /// The load that observes the cell as initialized must synchronize with
+    /// the initializing thread: use at least `Acquire`, unless the cell was
+    /// initialized on the current thread or synchronization is established
+    /// by other means (e.g. a fence or a lock).
+    ///
+    /// # Panics
+    /// Panics if `order` is `Release` or `AcqRel`, as for [`AtomicU8::load`].
+    pub unsafe fn get_ordered(&self, order: Ordering) -> Option<&T> {
+        if self.state.load(order) == READY {
             // SAFETY: We only access the value after initialization
             // and never modify it after that point
             unsafe { (*self.value.get()).as_ref() }
@@ -97,26 +401,241 @@ impl<T> StaticCell<T> {
         }
     }
 
-    /// Attempts to initialize the cell with a value.
-    pub fn try_init(&self, value: T) -> bool {
-        if self.initialized.compare_exchange(
-            false,
-            true,
-            Ordering::AcqRel,
-            Ordering::Relaxed,
-        ).is_ok() {
-            // SAFETY: We only modify the value during initialization
-            // and the atomic exchange ensures only one thread can initialize
-            unsafe { *self.value.get() = Some(value) };
-            true
-        } else {
-            false
+    /// Low-level variant of [`try_init`](Self::try_init) with explicit orderings
+    /// for the initializing compare-exchange.
+    ///
+    /// The store publishing the value as ready uses the release part of
+    /// `success`: `Release` for `Release` or `AcqRel`, `SeqCst` for `SeqCst`,
+    /// and `Relaxed` otherwise.
+    ///
+    /// # Safety
+    /// Readers on other threads only see the stored value if `success`
+    /// includes `Release` (`Release` or `AcqRel`) or synchronization is
+    /// established by other means. `Relaxed` is only sound when every
+    /// reader runs on the initializing thread.
+    ///
+    /// # Panics
+    /// Panics if `failure` is `Release` or `AcqRel`, as for
+    /// [`AtomicU8::compare_exchange`].
+    pub unsafe fn try_init_ordered(&self, value: T, success: Ordering, failure: Ordering) -> bool {
+        // SAFETY: the caller upholds the ordering contract
+        unsafe { self.set_ordered(value, success, failure) }.is_ok()
+    }
+
+    /// [`set`](Self::set) with the orderings of
+    /// [`try_init_ordered`](Self::try_init_ordered).
+    ///
+    /// # Safety
+    /// Same contract as [`try_init_ordered`](Self::try_init_ordered).
+    unsafe fn set_ordered(&self, value: T, success: Ordering, failure: Ordering) -> Result<(), T> {
+        if self.state.compare_exchange(UNINIT, INITIALIZING, success, failure).is_err() {
+            return Err(value);
         }
+        // SAFETY: We only modify the value during initialization
+        // and the atomic exchange ensures only one thread can initialize
+        unsafe { *self.value.get() = Some(value) };
+        let publish = match success {
+            Ordering::Release | Ordering::AcqRel => Ordering::Release,
+            Ordering::SeqCst => Ordering::SeqCst,
+            _ => Ordering::Relaxed,
+        };
+        self.state.store(READY, publish);
+        Ok(())
+    }
+}
+
+/// A reference to the value of a [`StaticCell`] that is known to be
+/// initialized.
+///
+/// Handles are only produced by [`StaticCell::get_or_init`] and
+/// [`StaticCell::get_or_try_init`], so taking one
+/// as a parameter encodes "initialization has happened" in the signature.
+/// It is a zero-cost wrapper around `&T` and dereferences to the value.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{Initialized, StaticCell};
+///
+/// struct Config {
+///     baud: u32,
+/// }
+///
+/// static CONFIG: StaticCell<Config> = StaticCell::new();
+///
+/// fn open_uart(config: Initialized<'static, Config>) -> u32 {
+///     config.baud
+/// }
+///
+/// let config = CONFIG.get_or_init(|| Config { baud: 115_200 });
+/// assert_eq!(open_uart(config), 115_200);
+/// ```
+pub struct Initialized<'a, T>(&'a T);
+
+impl<'a, T> Initialized<'a, T> {
+    /// Returns the underlying reference, with the cell's lifetime.
+    #[inline]
+    pub fn into_ref(self) -> &'a T {
+        self.0
+    }
+}
+
+impl<T> Clone for Initialized<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Initialized<'_, T> {}
+
+impl<T> core::ops::Deref for Initialized<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for Initialized<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The registered provider of execution context ids.
+static CONTEXT_ID_PROVIDER: StaticCell<fn() -> usize> = StaticCell::new();
+
+/// Registers the function identifying the current execution context.
+///
+/// [`StaticCell::get_or_init`] uses it to tell a reentrant call apart from
+/// another thread initializing the same cell concurrently. The provider
+/// must return a distinct, stable id per thread (or interrupt context).
+/// Returns `false` if a provider was already registered.
+///
+/// # Examples
+/// ```rust
+/// extern crate std;
+///
+/// use noir_macros_core::set_context_id_provider;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// fn thread_id() -> usize {
+///     static NEXT: AtomicUsize = AtomicUsize::new(0);
+///     std::thread_local!(static ID: usize = NEXT.fetch_add(1, Ordering::Relaxed));
+///     ID.with(|id| *id)
+/// }
+///
+/// assert!(set_context_id_provider(thread_id));
+/// ```
+pub fn set_context_id_provider(provider: fn() -> usize) -> bool {
+    CONTEXT_ID_PROVIDER.try_init(provider)
+}
+
+/// Returns the id of the current execution context, if it can be determined.
+fn current_context_id() -> Option<usize> {
+    match CONTEXT_ID_PROVIDER.get() {
+        Some(provider) => Some(provider()),
+        None if cfg!(target_os = "none") => Some(0),
+        None => thread_context_id(),
+    }
+}
+
+/// Identifies the current thread by the address of a thread-local, which
+/// is distinct for every live thread.
+#[cfg(any(test, feature = "std"))]
+fn thread_context_id() -> Option<usize> {
+    std::thread_local!(static ANCHOR: u8 = const { 0 });
+    ANCHOR.try_with(|anchor| anchor as *const u8 as usize).ok()
+}
+
+/// Without `std` there is no portable way to tell threads apart.
+#[cfg(not(any(test, feature = "std")))]
+fn thread_context_id() -> Option<usize> {
+    None
+}
+
+/// The registered yield hook of the crate's spin loops.
+static YIELD_HOOK: StaticCell<fn()> = StaticCell::new();
+
+/// Registers a function called on every iteration of the crate's spin loops.
+///
+/// Waiting in [`StaticCell::get_or_init`], [`SpinLock::lock`] or
+/// [`Barrier::wait`] busy-waits, which starves other tasks on a
+/// cooperative (non-preemptive) scheduler. The hook lets such a runtime
+/// yield to them instead. Without a hook, `core::hint::spin_loop` is used.
+/// Returns `false` if a hook was already registered.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::set_yield_hook;
+///
+/// fn yield_now() {
+///     // e.g. switch to the next ready task
+/// }
+///
+/// assert!(set_yield_hook(yield_now));
+/// assert!(!set_yield_hook(yield_now));
+/// ```
+pub fn set_yield_hook(hook: fn()) -> bool {
+    YIELD_HOOK.try_init(hook)
+}
+
+/// Runs one iteration of a spin loop's back-off.
+#[inline]
+fn relax() {
+    match YIELD_HOOK.get() {
+        Some(hook) => hook(),
+        None => core::hint::spin_loop(),
     }
 }
 
-/// Implements `Sync` for `StaticCell<T>` when `T: Sync`.
-unsafe impl<T: Sync> Sync for StaticCell<T> {}
+/// The enter and exit functions of [`critical_section!`].
+struct CriticalSectionImpl {
+    enter: fn(),
+    exit: fn(),
+}
+
+/// The registered implementation of [`critical_section!`].
+static CRITICAL_SECTION: StaticCell<CriticalSectionImpl> = StaticCell::new();
+
+/// Registers the functions entering and leaving a [`critical_section!`].
+///
+/// On bare metal, `enter` typically disables interrupts and `exit`
+/// re-enables them, so every driver shares one mechanism. Without a
+/// registration, critical sections only run their block, which suits
+/// hosted builds. Returns `false` if functions were already registered.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::set_critical_section_impl;
+///
+/// fn disable_interrupts() {
+///     // e.g. `cpsid i` on Cortex-M
+/// }
+///
+/// fn enable_interrupts() {
+///     // e.g. `cpsie i` on Cortex-M
+/// }
+///
+/// assert!(set_critical_section_impl(disable_interrupts, enable_interrupts));
+/// ```
+pub fn set_critical_section_impl(enter: fn(), exit: fn()) -> bool {
+    CRITICAL_SECTION.try_init(CriticalSectionImpl { enter, exit })
+}
+
+/// Runs `f` between the registered enter and exit functions.
+#[doc(hidden)]
+pub fn _critical_section<R>(f: impl FnOnce() -> R) -> R {
+    let Some(section) = CRITICAL_SECTION.get() else {
+        return f();
+    };
+    (section.enter)();
+    let _exit = Defer::new(section.exit);
+    f()
+}
+
+/// Implements `Sync` for `StaticCell<T>` when `T: Send + Sync`.
+unsafe impl<T: Send + Sync> Sync for StaticCell<T> {}
 
 /// Implements `Default` for `StaticCell<T>`.
 /// 
@@ -160,891 +679,7333 @@ impl<T> Default for StaticCell<T> {
 /// 
 /// static_cell!(STATE, AppState);
 /// ```
+///
+/// # Priority-Ordered Initialization
+/// Adding `priority = N` registers the cell in a global init list that
+/// [`init_all_by_priority`] runs in ascending priority order (lower numbers
+/// first), giving deterministic boot ordering across modules. The value is
+/// produced by `init = expr`, or by `Default::default()` when omitted.
+/// Registration uses linker sections and is available on ELF targets.
+///
+/// ```rust
+/// # #[cfg(target_os = "linux")] {
+/// use noir_macros_core::{init_all_by_priority, static_cell};
+///
+/// #[derive(Default)]
+/// struct Clocks { hz: u32 }
+///
+/// struct Uart { baud: u32 }
+///
+/// static_cell!(CLOCKS, Clocks, priority = 0);
+/// static_cell!(UART, Uart, priority = 10, init = Uart { baud: 115_200 });
+///
+/// init_all_by_priority();
+/// assert_eq!(CLOCKS.get().map(|c| c.hz), Some(0));
+/// assert_eq!(UART.get().map(|u| u.baud), Some(115_200));
+/// # }
+/// ```
+///
+/// # Link-Section Registration
+/// Adding `registered` places a [`CellRecord`] for the cell in the
+/// `noir_cells` link section, so a runtime can enumerate every registered
+/// singleton and initialize it with [`init_all_registered`]. The value is
+/// produced by `init = expr`, or by `Default::default()` when omitted.
+///
+/// The records are found through the `__start_noir_cells` and
+/// `__stop_noir_cells` symbols that GNU ld and LLD define for sections
+/// named like C identifiers. Custom linker scripts (as used by most
+/// `cortex-m` runtimes) must keep the section and define both symbols:
+///
+/// ```text
+/// .noir_cells : {
+///     __start_noir_cells = .;
+///     KEEP(*(noir_cells))
+///     __stop_noir_cells = .;
+/// } > FLASH
+/// ```
+///
+/// ```rust
+/// # #[cfg(target_os = "linux")] {
+/// use noir_macros_core::{init_all_registered, static_cell};
+///
+/// static_cell!(HEAP_SIZE, usize, registered, init = 4096);
+///
+/// init_all_registered();
+/// assert_eq!(HEAP_SIZE.get(), Some(&4096));
+/// # }
+/// ```
+///
+/// # Initialization Status
+/// Every cell declared with this macro is also listed in the
+/// `noir_cell_status` link section, so a supervisor can confirm that boot
+/// completed with [`all_statics_initialized`] or report the missing cells
+/// with [`uninitialized_statics`]. Custom linker scripts must keep this
+/// section the same way as `noir_cells`.
 #[macro_export]
 macro_rules! static_cell {
+    (@status $name:ident) => {
+        const _: () = {
+            fn is_initialized() -> bool {
+                $name.get().is_some()
+            }
+
+            #[cfg_attr(
+                any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"),
+                link_section = "noir_cell_status"
+            )]
+            #[used]
+            static STATUS: $crate::CellStatus = $crate::CellStatus {
+                name: stringify!($name),
+                is_initialized,
+            };
+        };
+    };
     ($name:ident, $type:ty) => {
         static $name: $crate::StaticCell<$type> = $crate::StaticCell::new();
+        $crate::static_cell!(@status $name);
     };
-}
+    ($name:ident, $type:ty, registered) => {
+        $crate::static_cell!($name, $type, registered, init = <$type as Default>::default());
+    };
+    ($name:ident, $type:ty, registered, init = $init:expr) => {
+        static $name: $crate::StaticCell<$type> = $crate::StaticCell::new();
+        $crate::static_cell!(@status $name);
 
-/// Verifies the size of a type at compile time.
-/// 
-/// # Understanding Type Size
-/// In systems programming, the exact size of types is crucial for:
-/// - Memory layout control
-/// - FFI (Foreign Function Interface) compatibility
-/// - Embedded systems constraints
-/// - Performance optimization
-/// 
-/// # Memory Alignment
-/// ```text
-/// struct Example {    Alignment Padding
-/// ┌─────────────┐    ┌─┐
-/// │  u32 (4B)   │    │ │
-/// ├─────────────┤    ├─┤
-/// │  u8 (1B)    │ -> │X│ <- 3 bytes padding
-/// ├─────────────┤    ├─┤
-/// │  u32 (4B)   │    │ │
-/// └─────────────┘    └─┘
-/// Total: 12 bytes
-/// ```
-/// 
-/// # Usage Examples
-/// ```rust
-/// use noir_macros_core::const_assert_size;
-/// // Basic size check
-/// const_assert_size!(u32, 4);
-/// 
-/// // Custom struct size verification
-/// #[repr(C)]
-/// struct Packet {
-///     header: u32,    // 4 bytes
-///     flags: u8,      // 1 byte
-///     _pad: [u8; 3],  // 3 bytes padding
-///     data: u32,      // 4 bytes
-/// }
-/// const_assert_size!(Packet, 12);
-/// ```
-/// 
-/// # Common Applications
-/// 1. Network protocol structures
-/// 2. Hardware interface types
-/// 3. Memory-mapped I/O
-/// 4. Binary file formats
-/// 
-/// # Best Practices
-/// 1. Always use with `#[repr(C)]` for predictable layout
-/// 2. Account for padding in size calculations
-/// 3. Document size assumptions
-/// 4. Use with alignment assertions
-#[macro_export]
-macro_rules! const_assert_size {
-    ($type:ty, $size:expr) => {
-        const _: () = assert!(core::mem::size_of::<$type>() == $size);
+        const _: () = {
+            // serializes concurrent walks so `$init` runs at most once
+            static INIT: $crate::SpinLock<()> = $crate::SpinLock::new(());
+
+            fn init() {
+                let _init = INIT.lock();
+                if $name.get().is_none() {
+                    let _ = $name.try_init($init);
+                }
+            }
+
+            #[cfg_attr(
+                any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"),
+                link_section = "noir_cells"
+            )]
+            #[used]
+            static RECORD: $crate::CellRecord = $crate::CellRecord {
+                name: stringify!($name),
+                init,
+            };
+        };
+    };
+    ($name:ident, $type:ty, priority = $priority:expr) => {
+        $crate::static_cell!($name, $type, priority = $priority, init = <$type as Default>::default());
+    };
+    ($name:ident, $type:ty, priority = $priority:expr, init = $init:expr) => {
+        static $name: $crate::StaticCell<$type> = $crate::StaticCell::new();
+        $crate::static_cell!(@status $name);
+
+        const _: () = {
+            // serializes concurrent walks so `$init` runs at most once
+            static INIT: $crate::SpinLock<()> = $crate::SpinLock::new(());
+
+            fn init() {
+                let _init = INIT.lock();
+                if $name.get().is_none() {
+                    let _ = $name.try_init($init);
+                }
+            }
+
+            #[cfg_attr(
+                any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"),
+                link_section = "noir_prio_init"
+            )]
+            #[used]
+            static ENTRY: $crate::PriorityInit = $crate::PriorityInit {
+                priority: $priority,
+                init,
+            };
+        };
     };
 }
 
-/// Verifies the alignment of a type at compile time.
-/// 
-/// # Understanding Alignment
-/// Memory alignment is crucial for:
-/// - CPU access efficiency
-/// - Hardware requirements
-/// - Platform compatibility
-/// - Performance optimization
-/// 
-/// # Alignment Visualization
-/// ```text
-/// Memory Address: 0  1  2  3  4  5  6  7
-///                ┌──┬──┬──┬──┬──┬──┬──┬──┐
-/// Aligned(4):    │  u32   │  u32   │  u32 
-///                └──┴──┴──┴──┴──┴──┴──┴──┘
-///                ↑     ↑     ↑     ↑
-///                Valid addresses for u32 (4-byte aligned)
-/// ```
-/// 
-/// # Usage Examples
+/// Returns the entries the linker collected into a registration section.
+///
+/// Each registering macro places `#[used]` statics of type `$ty` in one
+/// named ELF section; the linker brackets that section with `__start_` and
+/// `__stop_` symbols, which delimit the entries as a slice.
+macro_rules! section_entries {
+    ($ty:ty, $start:literal, $stop:literal) => {{
+        extern "Rust" {
+            #[link_name = $start]
+            static START: $ty;
+            #[link_name = $stop]
+            static STOP: $ty;
+        }
+
+        // SAFETY: the linker defines these symbols around the section, which
+        // only holds `$ty` entries (including the crate's sentinel entry)
+        unsafe {
+            let start = core::ptr::addr_of!(START);
+            let stop = core::ptr::addr_of!(STOP);
+            let len = (stop as usize - start as usize) / core::mem::size_of::<$ty>();
+            core::slice::from_raw_parts(start, len)
+        }
+    }};
+}
+
+/// An entry in the priority-ordered init list built by `static_cell!`.
+#[doc(hidden)]
+#[repr(C)]
+pub struct PriorityInit {
+    pub priority: u32,
+    pub init: fn(),
+}
+
+// Guarantees the section exists, so its bounds resolve even without entries.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+#[link_section = "noir_prio_init"]
+#[used]
+static PRIORITY_INIT_SENTINEL: PriorityInit = PriorityInit {
+    priority: u32::MAX,
+    init: || {},
+};
+
+/// Runs every `static_cell!` registered with a priority, lowest first.
+///
+/// Cells with equal priority run in link order. Already initialized cells
+/// are left untouched, so calling this more than once is harmless, and
+/// concurrent calls run each initializer at most once.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn init_all_by_priority() {
+    let entries = section_entries!(PriorityInit, "__start_noir_prio_init", "__stop_noir_prio_init");
+
+    let mut last: Option<u32> = None;
+    loop {
+        let next = entries
+            .iter()
+            .map(|entry| entry.priority)
+            .filter(|&priority| last.map_or(true, |last| priority > last))
+            .min();
+        let Some(priority) = next else { break };
+        for entry in entries.iter().filter(|entry| entry.priority == priority) {
+            (entry.init)();
+        }
+        last = Some(priority);
+    }
+}
+
+/// The registration record `static_cell!(.., registered)` places in the
+/// `noir_cells` link section.
+#[repr(C)]
+pub struct CellRecord {
+    /// The name of the registered static.
+    pub name: &'static str,
+    /// Initializes the cell if it is still empty.
+    pub init: fn(),
+}
+
+// Guarantees the section exists, so its bounds resolve even without cells.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+#[link_section = "noir_cells"]
+#[used]
+static CELL_RECORD_SENTINEL: CellRecord = CellRecord {
+    name: "",
+    init: || {},
+};
+
+/// Returns the records of every `static_cell!` declared with `registered`.
+///
+/// The crate's own sentinel record is filtered out.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn registered_cells() -> impl Iterator<Item = &'static CellRecord> {
+    section_entries!(CellRecord, "__start_noir_cells", "__stop_noir_cells")
+        .iter()
+        .filter(|record| !core::ptr::eq(*record, &CELL_RECORD_SENTINEL))
+}
+
+/// Initializes every cell in `records`, in order.
+///
+/// [`init_all_registered`] passes the records of the `noir_cells` section;
+/// calling this directly allows walking a table built by other means, such
+/// as a simulated section in host-side tests.
+pub fn init_registered<'a>(records: impl IntoIterator<Item = &'a CellRecord>) {
+    for record in records {
+        (record.init)();
+    }
+}
+
+/// Initializes every `static_cell!` declared with `registered`, in link
+/// order.
+///
+/// Already initialized cells are left untouched, so calling this more than
+/// once is harmless, and concurrent calls run each initializer at most once.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn init_all_registered() {
+    init_registered(registered_cells());
+}
+
+/// The status record every `static_cell!` places in the `noir_cell_status`
+/// link section.
+#[doc(hidden)]
+#[repr(C)]
+pub struct CellStatus {
+    pub name: &'static str,
+    pub is_initialized: fn() -> bool,
+}
+
+// Guarantees the section exists, so its bounds resolve even without cells.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+#[link_section = "noir_cell_status"]
+#[used]
+static CELL_STATUS_SENTINEL: CellStatus = CellStatus {
+    name: "",
+    is_initialized: || true,
+};
+
+/// Returns the names of the `static_cell!` cells that are not initialized
+/// yet, in link order.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn uninitialized_statics() -> impl Iterator<Item = &'static str> {
+    section_entries!(CellStatus, "__start_noir_cell_status", "__stop_noir_cell_status")
+        .iter()
+        .filter(|status| !(status.is_initialized)())
+        .map(|status| status.name)
+}
+
+/// Returns true once every cell declared with `static_cell!` is initialized.
+///
+/// # Examples
 /// ```rust
-/// use noir_macros_core::const_assert_align;
-/// 
-/// // Basic alignment checks
-/// const_assert_align!(u32, 4);
-/// const_assert_align!(u64, 8);
-/// 
-/// // Custom aligned types
-/// #[repr(align(16))]
-/// struct SimdVector {
-///     data: [f32; 4],
-/// }
-/// const_assert_align!(SimdVector, 16);
+/// # #[cfg(target_os = "linux")] {
+/// use noir_macros_core::{all_statics_initialized, static_cell};
+///
+/// static_cell!(CLOCK_HZ, u32);
+///
+/// assert!(!all_statics_initialized());
+/// CLOCK_HZ.try_init(48_000_000);
+/// assert!(all_statics_initialized());
+/// # }
 /// ```
-/// 
-/// # Common Use Cases
-/// 1. SIMD data structures
-/// 2. DMA buffers
-/// 3. Hardware interfaces
-/// 4. Cache-line optimization
-/// 
-/// # Best Practices
-/// 1. Use `#[repr(align(N))]` for custom alignment
-/// 2. Consider cache line sizes (usually 64 bytes)
-/// 3. Document alignment requirements
-/// 4. Pair with size assertions
-#[macro_export]
-macro_rules! const_assert_align {
-    ($type:ty, $align:expr) => {
-        const _: () = assert!(core::mem::align_of::<$type>() == $align);
-    };
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn all_statics_initialized() -> bool {
+    uninitialized_statics().next().is_none()
 }
 
-/// Creates a compile-time string literal.
-/// 
-/// # Understanding Const Strings
-/// Const strings are string literals that are:
-/// - Evaluated at compile time
-/// - Stored in the binary
-/// - Zero runtime overhead
-/// - Type checked at compile time
-/// 
-/// # Memory Layout
-/// ```text
-/// Static String in Binary:
-/// ┌────────────────────┐
-/// │ Length (usize)     │ <- Known at compile time
-/// ├────────────────────┤
-/// │ UTF-8 Bytes        │ <- Immutable data
-/// ├────────────────────┤
-/// │ NUL terminator     │ <- For C compatibility
-/// └────────────────────┘
-/// ```
-/// 
-/// # Usage Examples
+/// A fixed-capacity registry holding at most one `'static` value per type.
+///
+/// Each slot is a [`StaticCell`] keyed by the stored value's `TypeId`, so the
+/// registry can back type-keyed singletons (plugins, drivers, services) in
+/// `no_std` code without allocation.
+///
+/// # Examples
 /// ```rust
-/// use noir_macros_core::const_str;
+/// use noir_macros_core::TypeRegistry;
 ///
-/// // Basic usage
-/// const GREETING: &str = const_str!("Hello, World!");
-/// 
-/// // In static contexts
-/// static APP_NAME: &str = const_str!("MyApp");
-/// 
-/// // With escape sequences
-/// const PATH: &str = const_str!("C:\\Program Files\\App");
+/// struct Uart { baud: u32 }
+/// struct Timer { hz: u32 }
+///
+/// static REGISTRY: TypeRegistry<4> = TypeRegistry::new();
+/// static UART: Uart = Uart { baud: 115_200 };
+/// static TIMER: Timer = Timer { hz: 1_000 };
+///
+/// assert!(REGISTRY.register(&UART));
+/// assert!(REGISTRY.register(&TIMER));
+/// assert!(!REGISTRY.register(&UART)); // already registered
+///
+/// assert_eq!(REGISTRY.get::<Uart>().map(|u| u.baud), Some(115_200));
+/// assert_eq!(REGISTRY.get::<Timer>().map(|t| t.hz), Some(1_000));
 /// ```
-/// 
-/// # Common Applications
-/// 1. Error messages
-/// 2. Configuration strings
-/// 3. Static resources
-/// 4. Compile-time constants
-/// 
-/// # Best Practices
-/// 1. Use for truly constant strings
-/// 2. Consider UTF-8 implications
-/// 3. Document string purpose
-/// 4. Prefer over string literals for constants
-#[macro_export]
-macro_rules! const_str {
-    ($s:expr) => { $s };
+pub struct TypeRegistry<const N: usize> {
+    slots: [StaticCell<&'static (dyn Any + Sync)>; N],
+    registering: SpinLock<()>,
 }
 
-/// Performs compile-time type checks and assertions.
-/// 
-/// # Understanding Type Checks
-/// Type checking at compile time ensures:
-/// - Memory safety through layout verification
-/// - Size and alignment constraints
-/// - Value semantics validation
-/// - Performance characteristics
-/// 
-/// # Type Properties Verified
-/// ```text
-/// Type Requirements:
-/// ┌──────────────────┐
-/// │ POD Status       │ No custom Drop impl
-/// ├──────────────────┤
-/// │ Size Limits      │ Memory boundaries
-/// ├──────────────────┤
-/// │ Alignment        │ Memory layout
-/// └──────────────────┘
-/// ```
-/// 
-/// # Usage Examples
+impl<const N: usize> TypeRegistry<N> {
+    /// Creates an empty registry with room for `N` types.
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { StaticCell::new() }; N],
+            registering: SpinLock::new(()),
+        }
+    }
+
+    /// Registers `value` as the instance for type `T`.
+    ///
+    /// Returns `false` if `T` is already registered or every slot is taken.
+    /// Registrations are serialized by a lock, so concurrent calls for the
+    /// same type never both succeed; [`get`](Self::get) takes no lock.
+    pub fn register<T: Any + Sync>(&self, value: &'static T) -> bool {
+        let _registering = self.registering.lock();
+        if self.get::<T>().is_some() {
+            return false;
+        }
+        self.slots.iter().any(|slot| slot.try_init(value))
+    }
+
+    /// Returns the instance registered for type `T`, if any.
+    pub fn get<T: Any + Sync>(&self) -> Option<&'static T> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.get().copied())
+            .find_map(|value| {
+                let value: &'static dyn Any = value;
+                value.downcast_ref::<T>()
+            })
+    }
+}
+
+impl<const N: usize> Default for TypeRegistry<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A [`StaticCell`] whose value can be injected before first use.
+///
+/// Tests call [`inject`](Injectable::inject) to install a double; production
+/// code calls [`resolve`](Injectable::resolve) with the real factory, which
+/// only runs when nothing was injected beforehand. Usually declared through
+/// the [`injectable!`] macro.
+pub struct Injectable<T> {
+    cell: StaticCell<T>,
+}
+
+impl<T> Injectable<T> {
+    /// Creates an empty injectable slot.
+    pub const fn new() -> Self {
+        Self {
+            cell: StaticCell::new(),
+        }
+    }
+
+    /// Installs `value`, returning `false` if a value is already present.
+    pub fn inject(&self, value: T) -> bool {
+        self.cell.try_init(value)
+    }
+
+    /// Returns the injected value, or initializes it with `factory`.
+    ///
+    /// The factory is not called if a value was already injected or resolved.
+    pub fn resolve(&self, factory: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.cell.get() {
+            return value;
+        }
+        self.cell.try_init(factory());
+        self.cell.wait()
+    }
+}
+
+impl<T> Default for Injectable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Declares a static [`Injectable`] slot.
+///
+/// # Examples
 /// ```rust
-/// use noir_macros_core::type_check;
-/// 
-/// #[repr(C)]
-/// struct SafeType {
-///     data: u32,
+/// use noir_macros_core::injectable;
+///
+/// trait Clock: Sync {
+///     fn now(&self) -> u64;
 /// }
-/// 
-/// type_check! {
-///     ensure SafeType: {
-///         is_pod,                // Must be Plain Old Data
-///         max_size: 4,          // No larger than 4 bytes
-///         aligned_to: 4         // Must be 4-byte aligned
-///     }
+///
+/// struct HardwareClock;
+/// impl Clock for HardwareClock {
+///     fn now(&self) -> u64 { 1_700_000_000 }
+/// }
+///
+/// struct FakeClock;
+/// impl Clock for FakeClock {
+///     fn now(&self) -> u64 { 0 }
 /// }
+///
+/// injectable!(CLOCK: &'static dyn Clock);
+///
+/// // In a test, install the fake before anything resolves the clock
+/// assert!(CLOCK.inject(&FakeClock));
+///
+/// // Production wiring: the factory is skipped because a fake was injected
+/// let clock = CLOCK.resolve(|| &HardwareClock);
+/// assert_eq!(clock.now(), 0);
 /// ```
-/// 
-/// # Available Checks
-/// 1. `is_pod` - Ensures type has no custom Drop implementation
-/// 2. `max_size: N` - Verifies type size ≤ N bytes
-/// 3. `aligned_to: N` - Confirms type has N-byte alignment
-/// 
-/// # Best Practices
-/// 1. Use in safety-critical code
-/// 2. Document check rationale
-/// 3. Group related checks
-/// 4. Add error messages
-/// 
-/// # Implementation Details
-/// The checks are implemented using Rust's const evaluation system,
-/// ensuring all verifications happen at compile time with zero
-/// runtime overhead.
 #[macro_export]
-macro_rules! type_check {
-    (ensure $type:ty: { is_pod $(,)? }) => {
-        const _: () = assert!(core::mem::needs_drop::<$type>() == false);
-    };
-    (ensure $type:ty: { max_size: $size:expr $(,)? }) => {
-        const _: () = assert!(core::mem::size_of::<$type>() <= $size);
-    };
-    (ensure $type:ty: { aligned_to: $align:expr $(,)? }) => {
-        const _: () = assert!(core::mem::align_of::<$type>() == $align);
-    };
-    (ensure $type:ty: { $($check:ident $(: $val:expr)? ),+ $(,)? }) => {
-        $($crate::type_check!(ensure $type: { $check $(: $val)? });)+
+macro_rules! injectable {
+    ($vis:vis $name:ident: $type:ty) => {
+        $vis static $name: $crate::Injectable<$type> = $crate::Injectable::new();
     };
 }
 
-/// Creates a new vector with the given elements.
-/// 
-/// # Understanding Vectors
-/// A vector is a dynamic array that can grow or shrink in size. It's one of the most
-/// commonly used collection types in Rust because it provides:
-/// - Dynamic sizing (can grow/shrink)
-/// - Contiguous memory storage (fast access)
-/// - Automatic memory management
-/// 
-/// # Memory Layout
-/// ```text
-/// Vec<T>
-/// ┌─────────┬─────────┬──────────┐
-/// │ pointer │capacity │  length  │ (on stack)
-/// └───┬─────┴─────────┴──────────┘
-///     │
-///     v
-/// ┌───┬───┬───┬───┬─────┐
-/// │ 0 │ 1 │ 2 │ 3 │ ... │ (on heap)
-/// └───┴───┴───┴───┴─────┘
+/// A lazily initialized value backed by a [`StaticCell`].
+///
+/// The initializer runs on the first call to [`force`](Lazy::force); every
+/// later call returns the same value. `Lazy` also implements `Deref`, so
+/// the first dereference (e.g. `TABLE[3]` or `*TABLE`) runs the
+/// initializer just like `force`.
+///
+/// The initializer runs at most once, even under contention: callers that
+/// find it in progress on another thread wait for its value. An initializer
+/// that forces its own `Lazy` panics with "reentrant initialization", as
+/// [`StaticCell::get_or_init`] does, instead of waiting on itself.
+///
+/// # Reinitialization
+/// With the `reinit` feature, [`reinit`](Lazy::reinit) replaces the value
+/// seen through [`current`](Lazy::current), e.g. to reload configuration in
+/// a long-running program. The replacement lives in a slot guarded by a
+/// [`SpinLock`]: `current` holds the lock while its guard is alive, and
+/// `reinit` swaps the value under the lock and drops the previous one, so
+/// no memory is leaked and no reader sees a value being replaced. Keep
+/// guards short-lived, since `reinit` waits for them. [`force`](Lazy::force),
+/// [`get`](Lazy::get) and `Deref` hand out plain references and therefore
+/// always return the initial value.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::Lazy;
+///
+/// static TABLE: Lazy<[u32; 4]> = Lazy::new(|| [1, 2, 4, 8]);
+///
+/// assert_eq!(TABLE.force()[3], 8);
+/// assert_eq!(TABLE.len(), 4);
 /// ```
-/// 
-/// # Usage Patterns
-/// 1. Empty vector:
+pub struct Lazy<T, F = fn() -> T> {
+    cell: StaticCell<T>,
+    init: F,
+    initializing: SpinLock<()>,
+    #[cfg(feature = "reinit")]
+    replaced: SpinLock<Option<T>>,
+}
+
+impl<T, F: Fn() -> T> Lazy<T, F> {
+    /// Creates a lazy value that is initialized by `init` on first access.
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: StaticCell::new(),
+            init,
+            initializing: SpinLock::new(()),
+            #[cfg(feature = "reinit")]
+            replaced: SpinLock::new(None),
+        }
+    }
+
+    /// Returns the value, running the initializer if this is the first access.
+    ///
+    /// # Panics
+    /// Panics if called from this `Lazy`'s own initializer.
+    pub fn force(&self) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        // the running `get_or_init` below marks its context as the
+        // initializer; check it before waiting on the lock held for it
+        if let Some(id) = current_context_id() {
+            if self.cell.initializer.load(Ordering::Acquire) == id.wrapping_add(1) {
+                panic!("reentrant initialization");
+            }
+        }
+        let _initializing = self.initializing.lock();
+        self.cell.get_or_init(|| (self.init)()).into_ref()
+    }
+
+    /// Returns the value if it was already initialized, without running the
+    /// initializer.
+    ///
+    /// Unlike [`force`](Self::force), this lets code peek at the value on
+    /// paths where the initializer's side effects must not happen.
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+
+    /// Returns the value passed to the latest [`reinit`](Self::reinit), or
+    /// the initial value if there was none, running the initializer if
+    /// needed.
+    ///
+    /// The returned guard blocks `reinit` until it is dropped; see the
+    /// [type-level documentation](Lazy#reinitialization).
+    #[cfg(feature = "reinit")]
+    pub fn current(&self) -> LazyRef<'_, T> {
+        let replaced = self.replaced.lock();
+        if replaced.is_some() {
+            return LazyRef(LazyRefInner::Replaced(replaced));
+        }
+        drop(replaced);
+        LazyRef(LazyRefInner::Initial(self.force()))
+    }
+
+    /// Replaces the value returned by subsequent [`current`](Self::current)
+    /// calls, dropping the previous replacement.
+    #[cfg(feature = "reinit")]
+    pub fn reinit(&self, value: T) {
+        // bound so the previous value is dropped after the lock is released
+        let _previous = self.replaced.lock().replace(value);
+    }
+}
+
+/// A guard giving access to the latest value of a [`Lazy`], returned by
+/// [`Lazy::current`].
+#[cfg(feature = "reinit")]
+pub struct LazyRef<'a, T>(LazyRefInner<'a, T>);
+
+#[cfg(feature = "reinit")]
+enum LazyRefInner<'a, T> {
+    Initial(&'a T),
+    Replaced(SpinLockGuard<'a, Option<T>>),
+}
+
+#[cfg(feature = "reinit")]
+impl<T> core::ops::Deref for LazyRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match &self.0 {
+            LazyRefInner::Initial(value) => value,
+            LazyRefInner::Replaced(guard) => match guard.as_ref() {
+                Some(value) => value,
+                None => unreachable!(),
+            },
+        }
+    }
+}
+
+impl<T, F: Fn() -> T> core::ops::Deref for Lazy<T, F> {
+    type Target = T;
+
+    /// Returns the value, running the initializer on first access.
+    #[inline]
+    fn deref(&self) -> &T {
+        self.force()
+    }
+}
+
+/// A minimal spin-based mutual exclusion lock.
+///
+/// Suitable for short critical sections in `no_std` code where no OS mutex
+/// is available. Waiters busy-wait, so avoid holding the lock for long or
+/// locking it from an interrupt handler that may preempt the holder.
+///
+/// # Examples
 /// ```rust
-/// use noir_macros_core::vec;
-/// 
-/// let v: Vec<i32> = vec![];
+/// use noir_macros_core::SpinLock;
+///
+/// static COUNTER: SpinLock<u32> = SpinLock::new(0);
+///
+/// *COUNTER.lock() += 1;
+/// assert_eq!(*COUNTER.lock(), 1);
 /// ```
-/// 
-/// 2. Vector with repeated elements:
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+impl<T> SpinLock<T> {
+    /// Creates an unlocked lock holding `value`.
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquires the lock, spinning until it becomes available.
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            while self.locked.load(Ordering::Relaxed) {
+                relax();
+            }
+        }
+    }
+
+    /// Acquires the lock if it is currently free.
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| SpinLockGuard {
+                lock: self,
+                _marker: PhantomData,
+            })
+    }
+}
+
+// SAFETY: the lock hands out access to the value to one thread at a time
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+/// RAII guard giving access to the value of a locked [`SpinLock`].
+///
+/// The lock is released when the guard is dropped. Like `&mut T`, the
+/// guard can only be shared between threads when `T` is `Sync`:
+///
+/// ```rust,compile_fail
+/// use core::cell::Cell;
+/// use noir_macros_core::SpinLockGuard;
+///
+/// fn is_sync<T: Sync>() {}
+/// is_sync::<SpinLockGuard<'static, Cell<u32>>>();
+/// ```
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+    // `SpinLock<T>` is `Sync` for any `T: Send`; this keeps the guard from
+    // handing out `&T` to several threads unless `T: Sync`
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T> core::ops::Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the guard proves exclusive access
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> core::ops::DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the guard proves exclusive access
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+/// A spinning rendezvous point for `N` cores or threads.
+///
+/// Each call to [`wait`](Self::wait) blocks until `N` callers have arrived,
+/// then releases all of them at once. A generation counter lets the same
+/// barrier be reused for successive rendezvous, e.g. between boot stages.
+///
+/// # Examples
 /// ```rust
-/// // Creates [1, 1, 1, 1, 1]
-/// let v = vec![1; 5];
+/// use noir_macros_core::Barrier;
+///
+/// static BOOT: Barrier<1> = Barrier::new();
+///
+/// // with a single participant, waiting returns immediately
+/// BOOT.wait();
+/// BOOT.wait();
 /// ```
-/// 
-/// 3. Vector with specific elements:
+pub struct Barrier<const N: usize> {
+    arrived: AtomicUsize,
+    generation: AtomicUsize,
+}
+
+impl<const N: usize> Barrier<N> {
+    /// Creates a barrier for `N` participants.
+    ///
+    /// # Panics
+    /// Panics if `N` is zero.
+    pub const fn new() -> Self {
+        assert!(N > 0, "Barrier needs at least one participant");
+        Self {
+            arrived: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Spins until all `N` participants have called `wait`.
+    pub fn wait(&self) {
+        let generation = self.generation.load(Ordering::Acquire);
+        if self.arrived.fetch_add(1, Ordering::AcqRel) + 1 == N {
+            // last to arrive: reset for the next round, then release everyone
+            self.arrived.store(0, Ordering::Relaxed);
+            self.generation.fetch_add(1, Ordering::Release);
+        } else {
+            while self.generation.load(Ordering::Acquire) == generation {
+                relax();
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for Barrier<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fixed-size pool of reusable objects, such as DMA buffers or messages.
+///
+/// [`acquire`](Self::acquire) hands out a free slot as a [`PoolGuard`];
+/// dropping the guard returns the slot. Slot occupancy is tracked in an
+/// atomic bitmap, so `N` is limited to the bit width of `usize`. Each slot
+/// is created with `T::default()` on first use and keeps its contents
+/// between uses.
+///
+/// # Examples
 /// ```rust
-/// let v = vec![1, 2, 3, 4, 5];
+/// use noir_macros_core::Pool;
+///
+/// static BUFFERS: Pool<[u8; 32], 2> = Pool::new();
+///
+/// let mut first = BUFFERS.acquire().unwrap();
+/// first[0] = 0xAA;
+/// let second = BUFFERS.acquire().unwrap();
+/// assert!(BUFFERS.acquire().is_none());
+///
+/// drop(second);
+/// assert!(BUFFERS.acquire().is_some());
 /// ```
-/// 
-/// # Performance Considerations
-/// - Initial allocation happens on the heap
-/// - Capacity doubles when more space is needed
-/// - Consider pre-allocating with known size
-/// 
-/// # Best Practices
-/// 1. Use `with_capacity` when size is known
-/// 2. Clear with `clear()` instead of reassigning
-/// 3. Use `drain()` to remove and reuse elements
-/// 4. Consider `Vec::new()` for empty vectors
-#[macro_export]
-macro_rules! vec {
-    () => {
-        ::core::iter::Iterator::collect::<Vec<_>>(::core::iter::empty())
-    };
-    ($elem:expr; $n:expr) => {
-        ::core::iter::repeat($elem).take($n).collect::<Vec<_>>()
-    };
-    ($($x:expr),+ $(,)?) => {
-        <[_]>::into_vec(Box::new([$($x),+]))
-    };
+pub struct Pool<T, const N: usize> {
+    in_use: AtomicUsize,
+    slots: [StaticCell<UnsafeCell<T>>; N],
 }
 
-/// Creates a fixed-size array with the given elements.
-/// 
-/// # Understanding Arrays
-/// Arrays in Rust are fixed-size sequences of elements stored in contiguous memory.
-/// Unlike vectors, their size is part of their type and cannot change.
-/// 
-/// # Key Characteristics
-/// - Fixed size known at compile time
-/// - Stored entirely on the stack
-/// - Zero runtime overhead
-/// - Direct indexing without bounds checking
-/// 
-/// # Memory Layout
-/// ```text
-/// [T; N] (on stack)
-/// ┌───┬───┬───┬───┐
-/// │ 0 │ 1 │ 2 │ 3 │
-/// └───┴───┴───┴───┘
+impl<T, const N: usize> Pool<T, N> {
+    /// Creates a pool with `N` free slots.
+    ///
+    /// # Panics
+    /// Panics (or fails const evaluation) if `N` exceeds the bit width of
+    /// `usize`.
+    pub const fn new() -> Self {
+        assert!(N <= usize::BITS as usize, "Pool supports at most usize::BITS slots");
+        Self {
+            in_use: AtomicUsize::new(0),
+            slots: [const { StaticCell::new() }; N],
+        }
+    }
+
+    /// Returns the number of slots currently handed out.
+    pub fn in_use(&self) -> usize {
+        self.in_use.load(Ordering::Relaxed).count_ones() as usize
+    }
+}
+
+impl<T: Default, const N: usize> Pool<T, N> {
+    /// Takes a free slot, or returns `None` if all `N` slots are in use.
+    pub fn acquire(&self) -> Option<PoolGuard<'_, T, N>> {
+        let mut claimed = 0;
+        self.in_use
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |in_use| {
+                let index = (!in_use).trailing_zeros() as usize;
+                claimed = index;
+                (index < N).then(|| in_use | (1 << index))
+            })
+            .ok()?;
+
+        let slot = &self.slots[claimed];
+        if slot.get().is_none() {
+            slot.try_init(UnsafeCell::new(T::default()));
+        }
+        Some(PoolGuard {
+            pool: self,
+            index: claimed,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T, const N: usize> Default for Pool<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: each slot is accessed only through the guard holding its bit, so
+// values move between threads but are never shared.
+unsafe impl<T: Send, const N: usize> Sync for Pool<T, N> {}
+
+/// Exclusive access to a slot of a [`Pool`].
+///
+/// The slot is returned to the pool when the guard is dropped. Like
+/// `&mut T`, the guard can only be shared between threads when `T` is
+/// `Sync`:
+///
+/// ```rust,compile_fail
+/// use core::cell::Cell;
+/// use noir_macros_core::PoolGuard;
+///
+/// fn is_sync<T: Sync>() {}
+/// is_sync::<PoolGuard<'static, Cell<u32>, 4>>();
 /// ```
-/// 
-/// # Usage Examples
+pub struct PoolGuard<'a, T, const N: usize> {
+    pool: &'a Pool<T, N>,
+    index: usize,
+    // `Pool<T, N>` is `Sync` for any `T: Send`; this keeps the guard from
+    // handing out `&T` to several threads unless `T: Sync`
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<T, const N: usize> core::ops::Deref for PoolGuard<'_, T, N> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the guard's bit grants exclusive access to an initialized slot
+        unsafe { &*self.pool.slots[self.index].wait().get() }
+    }
+}
+
+impl<T, const N: usize> core::ops::DerefMut for PoolGuard<'_, T, N> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the guard's bit grants exclusive access to an initialized slot
+        unsafe { &mut *self.pool.slots[self.index].wait().get() }
+    }
+}
+
+impl<T, const N: usize> Drop for PoolGuard<'_, T, N> {
+    fn drop(&mut self) {
+        self.pool.in_use.fetch_and(!(1 << self.index), Ordering::Release);
+    }
+}
+
+/// A fixed-capacity map from generation-tagged [`Handle`]s to values.
+///
+/// [`insert`](Self::insert) stores a value and returns an opaque handle.
+/// Each slot carries a generation counter that is bumped on every insert
+/// and remove, so a handle kept after its value was removed is rejected
+/// even once the slot has been reused. Like [`Pool`], slot occupancy is
+/// tracked in an atomic bitmap, so `N` is limited to the bit width of
+/// `usize`.
+///
+/// [`get`](Self::get) returns a [`SlotRef`] guard rather than a bare
+/// reference: a concurrent [`remove`](Self::remove) waits for outstanding
+/// guards before moving the value out.
+///
+/// # Examples
 /// ```rust
-/// use noir_macros_core::array;
+/// use noir_macros_core::SlotMap;
 ///
-/// // Empty array
-/// let empty: [i32; 0] = array![];
-/// 
-/// // Array with values
-/// let numbers = array![1, 2, 3, 4];
-/// 
-/// // Note: All elements must be of the same type
-/// // This would NOT work:
-/// // let mixed = array![1, 2.5, 3.7];  // Error: mixed types
-/// // for mixed types, use a tuple
-/// let mixed: (i32, f64) = (1, 2.5);
+/// static TEXTURES: SlotMap<&str, 4> = SlotMap::new();
+///
+/// let grass = TEXTURES.insert("grass.png").unwrap();
+/// assert_eq!(*TEXTURES.get(grass).unwrap(), "grass.png");
+///
+/// assert_eq!(TEXTURES.remove(grass), Some("grass.png"));
+/// assert!(TEXTURES.get(grass).is_none());
 /// ```
-/// 
-/// # Common Use Cases
-/// 1. Fixed-size data structures
-/// 2. Performance-critical code
-/// 3. Embedded systems
-/// 4. Stack-only allocations
-/// 
-/// # When to Use Arrays
-/// 1. Fixed-size data structures
-/// 2. Performance-critical code
-/// 3. Embedded systems
-/// 4. Stack-only allocations
-/// 
-/// # Best Practices
-/// 1. Use when size is known at compile time
-/// 2. Consider for small, fixed collections
-/// 3. Use with SIMD operations
-/// 4. Prefer over Vec for tiny sequences
-#[macro_export]
-macro_rules! array {
-    () => { [] };
-    ($($x:expr),+ $(,)?) => { [$($x),+] };
+pub struct SlotMap<T, const N: usize> {
+    in_use: AtomicUsize,
+    slots: [Slot<T>; N],
 }
 
-/// Prints formatted text to the standard output.
-/// 
-/// This macro provides formatted printing functionality in no_std environments.
-/// It validates format strings at compile time.
-#[macro_export]
-macro_rules! print {
-    ($($arg:tt)*) => {{
-        // Create a static buffer for output
-        static PRINT_BUFFER: $crate::StaticCell<$crate::Buffer> = $crate::StaticCell::new();
-        
-        // Initialize buffer if needed
-        if PRINT_BUFFER.try_init($crate::Buffer::with_capacity($crate::DEFAULT_BUFFER_SIZE)) {
-            // First time initialization
+/// A slot of a [`SlotMap`]. The generation is odd while a value is present.
+struct Slot<T> {
+    generation: AtomicU32,
+    readers: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// An opaque reference to a value stored in a [`SlotMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+impl<T, const N: usize> SlotMap<T, N> {
+    /// Creates an empty slot map with `N` slots.
+    ///
+    /// # Panics
+    /// Panics (or fails const evaluation) if `N` exceeds the bit width of
+    /// `usize`.
+    pub const fn new() -> Self {
+        assert!(N <= usize::BITS as usize, "SlotMap supports at most usize::BITS slots");
+        Self {
+            in_use: AtomicUsize::new(0),
+            slots: [const {
+                Slot {
+                    generation: AtomicU32::new(0),
+                    readers: AtomicUsize::new(0),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }; N],
         }
-        
-        // Get reference to buffer and format string
-        if let Some(buffer) = PRINT_BUFFER.get() {
-            unsafe {
-                *buffer.pos.get() = 0;
+    }
+
+    /// Stores `value`, or returns `None` if all `N` slots are occupied.
+    pub fn insert(&self, value: T) -> Option<Handle> {
+        let mut claimed = 0;
+        self.in_use
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |in_use| {
+                let index = (!in_use).trailing_zeros() as usize;
+                claimed = index;
+                (index < N).then(|| in_use | (1 << index))
+            })
+            .ok()?;
+
+        let slot = &self.slots[claimed];
+        // SAFETY: the claimed bit grants exclusive access to an empty slot
+        unsafe { (*slot.value.get()).write(value) };
+        let generation = slot.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        Some(Handle {
+            index: claimed as u32,
+            generation,
+        })
+    }
+
+    /// Returns the value for `handle`, or `None` if it was removed.
+    pub fn get(&self, handle: Handle) -> Option<SlotRef<'_, T>> {
+        let slot = self.slots.get(handle.index as usize)?;
+        // register as a reader before checking the generation, so that a
+        // concurrent remove either fails our check or waits for us
+        slot.readers.fetch_add(1, Ordering::SeqCst);
+        if slot.generation.load(Ordering::SeqCst) != handle.generation {
+            slot.readers.fetch_sub(1, Ordering::Release);
+            return None;
+        }
+        Some(SlotRef { slot })
+    }
+
+    /// Removes and returns the value for `handle`, or `None` if it was
+    /// already removed.
+    ///
+    /// Waits for any [`SlotRef`] to the value to be dropped first.
+    pub fn remove(&self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get(handle.index as usize)?;
+        if handle.generation & 1 == 0 {
+            return None;
+        }
+        slot.generation
+            .compare_exchange(handle.generation, handle.generation.wrapping_add(1), Ordering::SeqCst, Ordering::Relaxed)
+            .ok()?;
+        while slot.readers.load(Ordering::SeqCst) != 0 {
+            relax();
+        }
+
+        // SAFETY: the generation change stops new readers, existing ones are
+        // gone, and the slot was initialized by the matching insert
+        let value = unsafe { (*slot.value.get()).assume_init_read() };
+        self.in_use.fetch_and(!(1 << handle.index), Ordering::Release);
+        Some(value)
+    }
+
+    /// Returns the number of occupied slots.
+    pub fn len(&self) -> usize {
+        self.in_use.load(Ordering::Relaxed).count_ones() as usize
+    }
+
+    /// Returns true if no slot is occupied.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, const N: usize> Default for SlotMap<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for SlotMap<T, N> {
+    fn drop(&mut self) {
+        for slot in &mut self.slots {
+            if *slot.generation.get_mut() & 1 == 1 {
+                // SAFETY: an odd generation means the slot holds a value
+                unsafe { slot.value.get_mut().assume_init_drop() };
+            }
+        }
+    }
+}
+
+// SAFETY: values are shared through `SlotRef` and moved out by `remove`,
+// both guarded by the per-slot generation and reader count.
+unsafe impl<T: Send + Sync, const N: usize> Sync for SlotMap<T, N> {}
+
+/// Shared access to a value in a [`SlotMap`].
+///
+/// The value cannot be removed while the guard is alive.
+pub struct SlotRef<'a, T> {
+    slot: &'a Slot<T>,
+}
+
+impl<T> core::ops::Deref for SlotRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the guard's reader count keeps the value in place
+        unsafe { (*self.slot.value.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Drop for SlotRef<'_, T> {
+    fn drop(&mut self) {
+        self.slot.readers.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// A fixed-capacity interner for `'static` strings.
+///
+/// Each distinct string is stored once in a [`StaticCell`] slot and
+/// identified by a stable index, which makes it suitable for compact
+/// symbol tables, logging tags and protocol tag tables. Lookups use a
+/// linear scan, so keep `N` small.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::StringInterner;
+///
+/// static TAGS: StringInterner<8> = StringInterner::new();
+///
+/// let net = TAGS.intern("net");
+/// let fs = TAGS.intern("fs");
+/// assert_eq!(TAGS.intern("net"), net);
+/// assert_eq!(TAGS.resolve(fs), Some("fs"));
+/// ```
+pub struct StringInterner<const N: usize> {
+    slots: [StaticCell<&'static str>; N],
+}
+
+impl<const N: usize> StringInterner<N> {
+    /// Creates an empty interner with room for `N` strings.
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { StaticCell::new() }; N],
+        }
+    }
+
+    /// Returns the index of `s`, interning it if it is not yet present.
+    ///
+    /// # Panics
+    /// Panics if `s` is new and all `N` slots are taken.
+    pub fn intern(&self, s: &'static str) -> usize {
+        for (index, slot) in self.slots.iter().enumerate() {
+            let stored = match slot.get() {
+                Some(stored) => stored,
+                None if slot.try_init(s) => return index,
+                None => slot.wait(),
+            };
+            if *stored == s {
+                return index;
+            }
+        }
+        panic!("string interner capacity exceeded");
+    }
+
+    /// Returns the string interned at `index`, if any.
+    pub fn resolve(&self, index: usize) -> Option<&'static str> {
+        self.slots.get(index)?.get().copied()
+    }
+}
+
+impl<const N: usize> Default for StringInterner<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fixed-capacity interner for `'static` byte slices.
+///
+/// The byte counterpart of [`StringInterner`], for binary blobs such as
+/// protocol templates: equal contents share one slot and index, even when
+/// they come from different statics. Lookups use a linear scan comparing
+/// contents, so keep `N` small.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::ByteInterner;
+///
+/// static TEMPLATES: ByteInterner<4> = ByteInterner::new();
+///
+/// let ping = TEMPLATES.intern(&[0x7e, 0x01, 0x7e]);
+/// let ack = TEMPLATES.intern(b"\x7e\x06\x7e");
+/// assert_eq!(TEMPLATES.intern(b"\x7e\x01\x7e"), ping);
+/// assert_eq!(TEMPLATES.resolve(ack), Some(&[0x7e, 0x06, 0x7e][..]));
+/// ```
+pub struct ByteInterner<const N: usize> {
+    slots: [StaticCell<&'static [u8]>; N],
+}
+
+impl<const N: usize> ByteInterner<N> {
+    /// Creates an empty interner with room for `N` blobs.
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { StaticCell::new() }; N],
+        }
+    }
+
+    /// Returns the index of `bytes`, interning them if no slot holds the
+    /// same contents yet.
+    ///
+    /// # Panics
+    /// Panics if `bytes` are new and all `N` slots are taken.
+    pub fn intern(&self, bytes: &'static [u8]) -> usize {
+        for (index, slot) in self.slots.iter().enumerate() {
+            let stored = match slot.get() {
+                Some(stored) => stored,
+                None if slot.try_init(bytes) => return index,
+                None => slot.wait(),
+            };
+            if *stored == bytes {
+                return index;
+            }
+        }
+        panic!("byte interner capacity exceeded");
+    }
+
+    /// Returns the bytes interned at `index`, if any.
+    pub fn resolve(&self, index: usize) -> Option<&'static [u8]> {
+        self.slots.get(index)?.get().copied()
+    }
+}
+
+impl<const N: usize> Default for ByteInterner<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fixed dictionary of `N` entries, set once and then read-only.
+///
+/// For small key-value tables read at boot, such as configuration, a
+/// linear scan over an array is smaller and often faster than a hash map,
+/// and needs no hasher or allocator. Keys are compared with `Eq`; the first
+/// matching entry wins.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::StaticDict;
+///
+/// static PINS: StaticDict<&str, u8, 2> = StaticDict::new();
+///
+/// assert!(PINS.init([("led", 13), ("button", 2)]));
+/// assert_eq!(PINS.get(&"led"), Some(&13));
+/// assert_eq!(PINS.get(&"buzzer"), None);
+/// ```
+pub struct StaticDict<K, V, const N: usize> {
+    entries: StaticCell<[(K, V); N]>,
+}
+
+impl<K, V, const N: usize> StaticDict<K, V, N> {
+    /// Creates a dictionary with no entries set.
+    pub const fn new() -> Self {
+        Self {
+            entries: StaticCell::new(),
+        }
+    }
+
+    /// Sets the entries, returning `false` if they were already set.
+    pub fn init(&self, entries: [(K, V); N]) -> bool {
+        self.entries.try_init(entries)
+    }
+}
+
+impl<K: Eq, V, const N: usize> StaticDict<K, V, N> {
+    /// Returns the value for `key`, or `None` if the key is missing or the
+    /// entries were not set yet.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries
+            .get()?
+            .iter()
+            .find(|(candidate, _)| candidate == key)
+            .map(|(_, value)| value)
+    }
+}
+
+impl<K, V, const N: usize> Default for StaticDict<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A shared handle to a hardware watchdog's pet (kick) routine.
+///
+/// The routine is registered once at startup and can then be called from
+/// any place that needs to keep the watchdog alive. Petting before
+/// registration is a no-op.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::Watchdog;
+///
+/// static WATCHDOG: Watchdog = Watchdog::new();
+///
+/// fn kick_hardware() {
+///     // e.g. write the reload key to the watchdog register
+/// }
+///
+/// WATCHDOG.pet(); // not registered yet, does nothing
+/// assert!(WATCHDOG.init(kick_hardware));
+/// WATCHDOG.pet();
+/// ```
+pub struct Watchdog {
+    pet: StaticCell<fn()>,
+}
+
+impl Watchdog {
+    /// Creates a watchdog handle with no pet routine registered.
+    pub const fn new() -> Self {
+        Self {
+            pet: StaticCell::new(),
+        }
+    }
+
+    /// Registers the pet routine, returning `false` if one is already set.
+    pub fn init(&self, pet: fn()) -> bool {
+        self.pet.try_init(pet)
+    }
+
+    /// Invokes the registered pet routine, if any.
+    #[inline]
+    pub fn pet(&self) {
+        if let Some(pet) = self.pet.get() {
+            pet();
+        }
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Enforces that a peripheral is initialized exactly once.
+///
+/// The first [`acquire`](Self::acquire) returns a [`HardwareToken`], which
+/// init code can require as proof that it is the single initialization.
+/// Any later call panics: unlike `try_init` returning `false`, a second
+/// initialization cannot be silently ignored.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{HardwareGuard, HardwareToken};
+///
+/// static UART0: HardwareGuard = HardwareGuard::new();
+///
+/// fn init_uart(_token: HardwareToken) {
+///     // configure baud rate, pins, ...
+/// }
+///
+/// init_uart(UART0.acquire());
+/// ```
+///
+/// ```rust,should_panic
+/// use noir_macros_core::HardwareGuard;
+///
+/// static UART0: HardwareGuard = HardwareGuard::new();
+///
+/// UART0.acquire();
+/// UART0.acquire(); // panics: already initialized
+/// ```
+pub struct HardwareGuard {
+    acquired: StaticCell<()>,
+}
+
+/// Proof that a [`HardwareGuard`] was acquired for the first time.
+///
+/// It can only be obtained from [`HardwareGuard::acquire`] and cannot be
+/// cloned.
+#[derive(Debug)]
+pub struct HardwareToken {
+    _private: (),
+}
+
+impl HardwareGuard {
+    /// Creates a guard that has not been acquired yet.
+    pub const fn new() -> Self {
+        Self {
+            acquired: StaticCell::new(),
+        }
+    }
+
+    /// Returns the token on the first call.
+    ///
+    /// # Panics
+    /// Panics, reporting the caller's location, if the guard was already
+    /// acquired.
+    #[track_caller]
+    pub fn acquire(&self) -> HardwareToken {
+        if !self.acquired.try_init(()) {
+            panic!("hardware already initialized");
+        }
+        HardwareToken { _private: () }
+    }
+
+    /// Returns true if the guard was acquired.
+    pub fn is_acquired(&self) -> bool {
+        self.acquired.is_initialized()
+    }
+}
+
+impl Default for HardwareGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A static value whose `&'static mut` can be taken exactly once.
+///
+/// This is the `Peripherals::take()` pattern for any `T`: the first
+/// [`take`](Self::take) hands out the only mutable reference and every later
+/// call returns `None`, so a driver can own its register block outright.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::StaticMut;
+///
+/// struct Registers {
+///     ctrl: u32,
+/// }
+///
+/// static REGS: StaticMut<Registers> = StaticMut::new(Registers { ctrl: 0 });
+///
+/// let regs = REGS.take().unwrap();
+/// regs.ctrl = 0x1;
+/// assert!(REGS.take().is_none());
+/// ```
+pub struct StaticMut<T> {
+    taken: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: the value is only reachable through the single reference handed
+// out by `take`, which may move to another thread.
+unsafe impl<T: Send> Sync for StaticMut<T> {}
+
+impl<T> StaticMut<T> {
+    /// Creates a cell holding `value` that has not been taken yet.
+    pub const fn new(value: T) -> Self {
+        Self {
+            taken: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Returns the mutable reference on the first call, `None` afterwards.
+    // the reference is handed out once, so it never aliases
+    #[allow(clippy::mut_from_ref)]
+    pub fn take(&'static self) -> Option<&'static mut T> {
+        if self.taken.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        // SAFETY: the swap above succeeds exactly once, so no other
+        // reference to the value exists.
+        Some(unsafe { &mut *self.value.get() })
+    }
+
+    /// Returns true if the reference was taken.
+    pub fn is_taken(&self) -> bool {
+        self.taken.load(Ordering::Acquire)
+    }
+}
+
+/// A shared monotonic time source, registered once at boot.
+///
+/// Drivers read ticks through [`now`](Self::now) without depending on the
+/// concrete timer. The tick unit is whatever the registered source
+/// returns, e.g. microseconds or raw timer counts.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::Timebase;
+///
+/// static TIME: Timebase = Timebase::new();
+///
+/// fn read_systick() -> u64 {
+///     // e.g. read a free-running hardware counter
+///     1_000
+/// }
+///
+/// assert_eq!(TIME.now(), 0); // not registered yet
+/// assert!(TIME.init(read_systick));
+/// assert_eq!(TIME.now(), 1_000);
+/// ```
+pub struct Timebase {
+    source: StaticCell<fn() -> u64>,
+}
+
+impl Timebase {
+    /// Creates a timebase with no tick source registered.
+    pub const fn new() -> Self {
+        Self {
+            source: StaticCell::new(),
+        }
+    }
+
+    /// Registers the tick source, returning `false` if one is already set.
+    pub fn init(&self, source: fn() -> u64) -> bool {
+        self.source.try_init(source)
+    }
+
+    /// Returns the current tick count, or 0 if no source is registered.
+    #[inline]
+    pub fn now(&self) -> u64 {
+        self.source.get().map_or(0, |source| source())
+    }
+}
+
+impl Default for Timebase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A configuration value set once at startup from a pluggable source.
+///
+/// [`init_from`](Self::init_from) takes the value from any source, such as
+/// a flash sector, a boot argument or a test fixture. With the `std`
+/// feature, [`init_from_env`](Self::init_from_env) additionally reads it
+/// from an environment variable.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::ConfigCell;
+///
+/// struct Config {
+///     baud_rate: u32,
+/// }
+///
+/// static CONFIG: ConfigCell<Config> = ConfigCell::new();
+///
+/// fn read_boot_args() -> Config {
+///     Config { baud_rate: 115_200 }
+/// }
+///
+/// assert!(CONFIG.init_from(read_boot_args));
+/// assert!(!CONFIG.init_from(|| Config { baud_rate: 9_600 }));
+/// assert_eq!(CONFIG.get().unwrap().baud_rate, 115_200);
+/// ```
+pub struct ConfigCell<T> {
+    value: StaticCell<T>,
+}
+
+impl<T> ConfigCell<T> {
+    /// Creates an unset configuration.
+    pub const fn new() -> Self {
+        Self {
+            value: StaticCell::new(),
+        }
+    }
+
+    /// Sets the configuration from `source`, returning `false` if it was
+    /// already set. `source` is not called in that case.
+    pub fn init_from(&self, source: impl FnOnce() -> T) -> bool {
+        self.value.get().is_none() && self.value.try_init(source())
+    }
+
+    /// Returns the configuration, or `None` if it was not set yet.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: core::str::FromStr> ConfigCell<T> {
+    /// Sets the configuration by parsing the environment variable `key`,
+    /// falling back to `default` if it is unset or does not parse.
+    ///
+    /// Returns `false` if the configuration was already set.
+    pub fn init_from_env(&self, key: &str, default: T) -> bool {
+        self.init_from(|| {
+            std::env::var(key)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        })
+    }
+}
+
+impl<T> Default for ConfigCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A one-shot fallible initialization that keeps the error as well as the
+/// value.
+///
+/// [`StaticCell::get_or_try_init`] leaves the cell empty on failure so the
+/// next call retries. `FallibleCell` instead caches the outcome of the
+/// first attempt, successful or not, for initializations that must not be
+/// repeated, such as a hardware self-check whose failure should be
+/// reported rather than retried.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::FallibleCell;
+///
+/// static RADIO: FallibleCell<u8, &str> = FallibleCell::new();
+///
+/// fn probe_radio() -> Result<u8, &'static str> {
+///     Err("no response on SPI")
+/// }
+///
+/// assert_eq!(RADIO.try_init_with(probe_radio), Err(&"no response on SPI"));
+/// // the failure is cached: the closure does not run again
+/// assert_eq!(RADIO.try_init_with(|| Ok(0x42)), Err(&"no response on SPI"));
+/// assert_eq!(RADIO.get(), Some(Err(&"no response on SPI")));
+/// ```
+pub struct FallibleCell<T, E> {
+    outcome: StaticCell<Result<T, E>>,
+}
+
+impl<T, E> FallibleCell<T, E> {
+    /// Creates a cell whose initialization has not been attempted.
+    pub const fn new() -> Self {
+        Self {
+            outcome: StaticCell::new(),
+        }
+    }
+
+    /// Runs `f` if no attempt was made yet, and returns the outcome of the
+    /// first attempt.
+    ///
+    /// As with [`StaticCell::get_or_init`], racing callers may each run `f`,
+    /// but only one outcome is kept and returned to all of them.
+    pub fn try_init_with(&self, f: impl FnOnce() -> Result<T, E>) -> Result<&T, &E> {
+        self.outcome.get_or_init(f).into_ref().as_ref()
+    }
+
+    /// Returns the outcome of the first attempt, or `None` if there was no
+    /// attempt yet.
+    pub fn get(&self) -> Option<Result<&T, &E>> {
+        self.outcome.get().map(Result::as_ref)
+    }
+}
+
+impl<T, E> Default for FallibleCell<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fixed-capacity text buffer that truncates instead of allocating.
+///
+/// Writes beyond `N` bytes are cut at a character boundary and reported as
+/// a `core::fmt::Error`; everything that fit is kept.
+///
+/// # Examples
+/// ```rust
+/// use core::fmt::Write;
+/// use noir_macros_core::ArrayBuffer;
+///
+/// let mut buffer = ArrayBuffer::<8>::new();
+/// write!(buffer, "{}", 42).unwrap();
+/// assert_eq!(buffer.as_str(), "42");
+/// assert!(write!(buffer, " overflow").is_err());
+/// assert_eq!(buffer.as_str(), "42 overf");
+/// ```
+pub struct ArrayBuffer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> ArrayBuffer<N> {
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self { bytes: [0; N], len: 0 }
+    }
+
+    /// Returns the text written so far.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: `write_str` only stores whole UTF-8 characters
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+}
+
+impl<const N: usize> Default for ArrayBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> core::fmt::Write for ArrayBuffer<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let space = N - self.len;
+        let mut take = s.len().min(space);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.bytes[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        if take == s.len() {
+            Ok(())
+        } else {
+            Err(core::fmt::Error)
+        }
+    }
+}
+
+/// Keeps the message of the first panic for later inspection.
+///
+/// Devices without a console can record the panic message from their
+/// `#[panic_handler]` with [`capture_panic!`] and report it later, e.g.
+/// from a fault logger or over a debug probe. Messages longer than `N`
+/// bytes are truncated. Reading the message after a reset additionally
+/// requires placing the buffer in RAM that the startup code does not clear,
+/// and validating its contents after a cold boot.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::PanicBuffer;
+///
+/// static LAST_PANIC: PanicBuffer<64> = PanicBuffer::new();
+///
+/// // inside the panic handler
+/// LAST_PANIC.capture(format_args!("{}", "index out of bounds"));
+///
+/// // later
+/// assert_eq!(LAST_PANIC.last_panic(), Some("index out of bounds"));
+/// ```
+pub struct PanicBuffer<const N: usize> {
+    message: StaticCell<ArrayBuffer<N>>,
+}
+
+impl<const N: usize> PanicBuffer<N> {
+    /// Creates an empty panic buffer.
+    pub const fn new() -> Self {
+        Self {
+            message: StaticCell::new(),
+        }
+    }
+
+    /// Records `message` unless a panic was already captured; returns
+    /// whether it was recorded.
+    pub fn capture(&self, message: core::fmt::Arguments) -> bool {
+        if self.message.get().is_some() {
+            return false;
+        }
+        let mut buffer = ArrayBuffer::new();
+        // a truncated message is still worth keeping
+        let _ = core::fmt::write(&mut buffer, message);
+        self.message.try_init(buffer)
+    }
+
+    /// Returns the captured panic message, if any.
+    pub fn last_panic(&self) -> Option<&str> {
+        self.message.get().map(ArrayBuffer::as_str)
+    }
+}
+
+impl<const N: usize> Default for PanicBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Records a `core::panic::PanicInfo` into a [`PanicBuffer`].
+///
+/// Meant to be called from a `#[panic_handler]`; the message is formatted
+/// with the panic location, like the standard library's panic output.
+///
+/// # Examples
+/// ```rust,ignore
+/// use noir_macros_core::{capture_panic, PanicBuffer};
+///
+/// static LAST_PANIC: PanicBuffer<128> = PanicBuffer::new();
+///
+/// #[panic_handler]
+/// fn panic(info: &core::panic::PanicInfo) -> ! {
+///     capture_panic!(LAST_PANIC, info);
+///     loop {}
+/// }
+/// ```
+#[macro_export]
+macro_rules! capture_panic {
+    ($buffer:expr, $info:expr) => {
+        $buffer.capture(core::format_args!("{}", $info))
+    };
+}
+
+/// Batches log lines in a fixed-size buffer and hands them to a handler
+/// in one call.
+///
+/// Each [`log`](Self::log) appends a line terminated by `\n`; the handler
+/// receives the accumulated text when the buffer cannot take the next line
+/// or on an explicit [`flush`](Self::flush). This suits handlers with a
+/// high per-call cost, such as RTT or semihosting. A single line longer
+/// than `N` bytes is truncated.
+///
+/// The handler is registered once, like [`Watchdog`]. Until then lines are
+/// buffered, and lines that no longer fit are dropped. The handler runs
+/// with the buffer locked, so it must not log through the same logger.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::BufferedLogger;
+///
+/// static LOG: BufferedLogger<256> = BufferedLogger::new();
+///
+/// fn semihosting_write(text: &str) {
+///     // one expensive host call for the whole batch
+///     assert_eq!(text, "boot\nclock ready: 48 MHz\n");
+/// }
+///
+/// assert!(LOG.init(semihosting_write));
+/// LOG.log(format_args!("boot"));
+/// LOG.log(format_args!("clock ready: {} MHz", 48));
+/// LOG.flush();
+/// ```
+pub struct BufferedLogger<const N: usize> {
+    handler: StaticCell<fn(&str)>,
+    lines: SpinLock<ArrayBuffer<N>>,
+}
+
+impl<const N: usize> BufferedLogger<N> {
+    /// Creates an empty logger with no handler registered.
+    pub const fn new() -> Self {
+        Self {
+            handler: StaticCell::new(),
+            lines: SpinLock::new(ArrayBuffer::new()),
+        }
+    }
+
+    /// Registers the handler, returning `false` if one is already set.
+    pub fn init(&self, handler: fn(&str)) -> bool {
+        self.handler.try_init(handler)
+    }
+
+    /// Appends `args` as one line, flushing first if it does not fit.
+    pub fn log(&self, args: core::fmt::Arguments) {
+        use core::fmt::Write as _;
+
+        let mut lines = self.lines.lock();
+        let start = lines.len;
+        if writeln!(lines, "{}", args).is_ok() {
+            return;
+        }
+
+        if start > 0 {
+            lines.len = start;
+            let Some(handler) = self.handler.get() else {
+                return;
+            };
+            handler(lines.as_str());
+            lines.len = 0;
+            if writeln!(lines, "{}", args).is_ok() {
+                return;
+            }
+        }
+        // the line alone exceeds the buffer and was truncated: emit what
+        // fits, or keep it buffered until a handler exists
+        if let Some(handler) = self.handler.get() {
+            if lines.len > 0 {
+                handler(lines.as_str());
+                lines.len = 0;
+            }
+        }
+    }
+
+    /// Emits all buffered lines in a single handler call.
+    ///
+    /// Does nothing if the buffer is empty or no handler is registered.
+    pub fn flush(&self) {
+        let mut lines = self.lines.lock();
+        if let Some(handler) = self.handler.get() {
+            if lines.len > 0 {
+                handler(lines.as_str());
+                lines.len = 0;
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for BufferedLogger<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A one-shot channel handing a single value from one init phase to another.
+///
+/// The value can be sent once and received once: the receiver takes it out
+/// of the cell, so later receives observe `None`.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::Handoff;
+///
+/// static BOOT_INFO: Handoff<u32> = Handoff::new();
+///
+/// // early boot
+/// assert_eq!(BOOT_INFO.send(0x8000), Ok(()));
+/// assert_eq!(BOOT_INFO.send(0x9000), Err(0x9000));
+///
+/// // later phase
+/// assert_eq!(BOOT_INFO.recv(), Some(0x8000));
+/// assert_eq!(BOOT_INFO.recv(), None);
+/// ```
+pub struct Handoff<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// Handoff states, advanced once each by `send` and `recv`
+const HANDOFF_EMPTY: u8 = 0;
+const HANDOFF_SENDING: u8 = 1;
+const HANDOFF_FULL: u8 = 2;
+const HANDOFF_RECEIVED: u8 = 3;
+
+impl<T> Handoff<T> {
+    /// Creates an empty channel.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(HANDOFF_EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Sends the value, handing it back if a value was already sent.
+    pub fn send(&self, value: T) -> Result<(), T> {
+        if self
+            .state
+            .compare_exchange(HANDOFF_EMPTY, HANDOFF_SENDING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(value);
+        }
+        // SAFETY: winning the exchange grants exclusive write access
+        unsafe { (*self.value.get()).write(value) };
+        self.state.store(HANDOFF_FULL, Ordering::Release);
+        Ok(())
+    }
+
+    /// Takes the sent value, or returns `None` if nothing was sent yet or
+    /// the value was already received.
+    pub fn recv(&self) -> Option<T> {
+        self.state
+            .compare_exchange(HANDOFF_FULL, HANDOFF_RECEIVED, Ordering::AcqRel, Ordering::Relaxed)
+            .ok()?;
+        // SAFETY: the exchange observed the completed send and grants
+        // exclusive access to the value, which is read exactly once
+        Some(unsafe { (*self.value.get()).assume_init_read() })
+    }
+}
+
+impl<T> Default for Handoff<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Handoff<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == HANDOFF_FULL {
+            // SAFETY: a sent value that was never received is still owned here
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+// SAFETY: the value is moved between threads but never shared, so `Send`
+// is sufficient.
+unsafe impl<T: Send> Sync for Handoff<T> {}
+
+/// A null-terminated C string built on first use and cached for FFI calls.
+///
+/// Created by [`lazy_cstr!`]; `N` is the text length plus the terminator.
+pub struct LazyCStr<const N: usize> {
+    text: &'static str,
+    bytes: StaticCell<[u8; N]>,
+}
+
+impl<const N: usize> LazyCStr<N> {
+    /// Creates the holder for `text`.
+    ///
+    /// # Panics
+    /// Panics (or fails const evaluation) if `text` contains a null byte or
+    /// `N` is not `text.len() + 1`.
+    pub const fn new(text: &'static str) -> Self {
+        assert!(N == text.len() + 1, "LazyCStr length must include the terminator");
+        let bytes = text.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            assert!(bytes[i] != 0, "C string literal contains an interior null byte");
+            i += 1;
+        }
+        Self {
+            text,
+            bytes: StaticCell::new(),
+        }
+    }
+
+    /// Returns the cached C string, building it on first use.
+    pub fn get(&'static self) -> &'static core::ffi::CStr {
+        if self.bytes.get().is_none() {
+            let mut bytes = [0u8; N];
+            bytes[..N - 1].copy_from_slice(self.text.as_bytes());
+            self.bytes.try_init(bytes);
+        }
+        let bytes = self.bytes.wait();
+        // SAFETY: `new` rejected interior nulls and the last byte is the terminator
+        unsafe { core::ffi::CStr::from_bytes_with_nul_unchecked(bytes) }
+    }
+}
+
+/// Declares a static [`LazyCStr`] for passing a string literal to C.
+///
+/// The literal is checked for interior null bytes at compile time; the
+/// null-terminated copy is built once, on first access, and reused after.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::lazy_cstr;
+///
+/// lazy_cstr!(pub DEVICE_NAME = "uart0");
+///
+/// assert_eq!(DEVICE_NAME.get().to_bytes_with_nul(), b"uart0\0");
+/// ```
+///
+/// Interior null bytes are rejected:
+/// ```rust,compile_fail
+/// use noir_macros_core::lazy_cstr;
+///
+/// lazy_cstr!(BAD = "ua\0rt");
+/// ```
+#[macro_export]
+macro_rules! lazy_cstr {
+    ($vis:vis $name:ident = $s:expr) => {
+        $vis static $name: $crate::LazyCStr<{ $s.len() + 1 }> = $crate::LazyCStr::new($s);
+    };
+}
+
+/// A slice computed on first use and shared as `&'static [T]`.
+///
+/// Created by [`lazy_slice!`]. The `Vec` returned by the init function is
+/// kept in a [`StaticCell`] that is never dropped, which is what makes the
+/// `'static` borrow sound.
+pub struct LazySlice<T> {
+    cell: StaticCell<Vec<T>>,
+    init: fn() -> Vec<T>,
+}
+
+impl<T> LazySlice<T> {
+    /// Creates a slice computed by `init` on first access.
+    pub const fn new(init: fn() -> Vec<T>) -> Self {
+        Self {
+            cell: StaticCell::new(),
+            init,
+        }
+    }
+
+    /// Returns the slice, computing it on first use.
+    pub fn get(&'static self) -> &'static [T] {
+        self.cell.get_or_init(self.init).into_ref()
+    }
+}
+
+/// Declares a static [`LazySlice`] built once at runtime.
+///
+/// Useful for lookup tables that depend on runtime data but are read-only
+/// afterwards. The init expression must be a non-capturing closure or a
+/// function returning `Vec<T>`.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::lazy_slice;
+///
+/// lazy_slice!(pub SQUARES: u32 = || (0..8).map(|n| n * n).collect());
+///
+/// assert_eq!(SQUARES.get()[3], 9);
+/// assert_eq!(SQUARES.get().len(), 8);
+/// ```
+#[macro_export]
+macro_rules! lazy_slice {
+    ($vis:vis $name:ident: $type:ty = $init:expr) => {
+        $vis static $name: $crate::LazySlice<$type> = $crate::LazySlice::new($init);
+    };
+}
+
+/// A string formatted once and shared as `&'static str`.
+///
+/// Created by [`format_cache!`], one per arm. The formatted `String` lives
+/// in a [`StaticCell`] that is never dropped, which is what makes the
+/// `'static` borrow sound.
+pub struct FormatSlot {
+    text: StaticCell<alloc::string::String>,
+}
+
+impl FormatSlot {
+    /// Creates a slot with no string formatted yet.
+    pub const fn new() -> Self {
+        Self {
+            text: StaticCell::new(),
+        }
+    }
+
+    /// Returns the string, calling `format` on first use.
+    pub fn get(&'static self, format: impl FnOnce() -> alloc::string::String) -> &'static str {
+        self.text.get_or_init(format).into_ref()
+    }
+}
+
+impl Default for FormatSlot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Defines a function mapping enum variants to strings formatted on first
+/// request.
+///
+/// Each arm pairs a pattern with an expression producing a `String`. The
+/// expression runs the first time a matching value is passed and its
+/// result is cached for the rest of the program, which avoids reformatting
+/// identical status messages in loops. The arms form a `match`, so they
+/// must cover every value:
+///
+/// ```rust,compile_fail
+/// use noir_macros_core::format_cache;
+///
+/// enum Link {
+///     Up,
+///     Down,
+/// }
+///
+/// format_cache! {
+///     fn link_message(link: Link) {
+///         Link::Up => format!("link up"),
+///     }
+/// }
+/// ```
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::format_cache;
+///
+/// #[derive(Clone, Copy)]
+/// enum Link {
+///     Up,
+///     Down,
+/// }
+///
+/// const PORT: u8 = 2;
+///
+/// format_cache! {
+///     pub fn link_message(link: Link) {
+///         Link::Up => format!("port {} link up", PORT),
+///         Link::Down => format!("port {} link down", PORT),
+///     }
+/// }
+///
+/// assert_eq!(link_message(Link::Down), "port 2 link down");
+/// ```
+#[macro_export]
+macro_rules! format_cache {
+    (
+        $vis:vis fn $name:ident($arg:ident: $type:ty) {
+            $($variant:pat => $format:expr),+ $(,)?
+        }
+    ) => {
+        $vis fn $name($arg: $type) -> &'static str {
+            match $arg {
+                $(
+                    $variant => {
+                        static SLOT: $crate::FormatSlot = $crate::FormatSlot::new();
+                        SLOT.get(|| $format)
+                    }
+                )+
+            }
+        }
+    };
+}
+
+/// A bump allocator over `N` elements allocated on first use.
+///
+/// Created by [`lazy_arena!`]. The backing `Box<[T]>` is filled with
+/// `T::default()` on the first [`alloc`](Self::alloc) and lives in a
+/// [`StaticCell`] that is never dropped. Each call hands out the next
+/// unused elements as a `&'static mut [T]`; memory is never reclaimed.
+pub struct Arena<T, const N: usize> {
+    storage: StaticCell<alloc::boxed::Box<[UnsafeCell<T>]>>,
+    next: AtomicUsize,
+}
+
+impl<T, const N: usize> Arena<T, N> {
+    /// Creates an arena whose storage is allocated on first use.
+    pub const fn new() -> Self {
+        Self {
+            storage: StaticCell::new(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of elements not handed out yet.
+    pub fn remaining(&self) -> usize {
+        N - self.next.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Default, const N: usize> Arena<T, N> {
+    /// Hands out the next `len` elements, or `None` if fewer remain.
+    // each range is handed out once, so the mutable borrows never alias
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&'static self, len: usize) -> Option<&'static mut [T]> {
+        let start = self
+            .next
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                next.checked_add(len).filter(|&end| end <= N)
+            })
+            .ok()?;
+
+        if self.storage.get().is_none() {
+            self.storage.try_init((0..N).map(|_| UnsafeCell::new(T::default())).collect());
+        }
+        let base = UnsafeCell::raw_get(self.storage.wait().as_ptr());
+        // SAFETY: the bump pointer hands every element out at most once, so
+        // the range is in bounds and not aliased
+        Some(unsafe { core::slice::from_raw_parts_mut(base.add(start), len) })
+    }
+}
+
+impl<T, const N: usize> Default for Arena<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: handed-out ranges are disjoint, so elements are never shared
+// between threads, only sent.
+unsafe impl<T: Send, const N: usize> Sync for Arena<T, N> {}
+
+/// Declares a static [`Arena`] of `SIZE` elements allocated on first use.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::lazy_arena;
+///
+/// lazy_arena!(pub SAMPLES: u16, 1024);
+///
+/// let left = SAMPLES.alloc(512).unwrap();
+/// let right = SAMPLES.alloc(512).unwrap();
+/// left[0] = 1;
+/// right[0] = 2;
+/// assert!(SAMPLES.alloc(1).is_none());
+/// ```
+#[macro_export]
+macro_rules! lazy_arena {
+    ($vis:vis $name:ident: $type:ty, $size:expr) => {
+        $vis static $name: $crate::Arena<$type, { $size }> = $crate::Arena::new();
+    };
+}
+
+/// A process-wide RNG seed, set once at startup and read everywhere.
+///
+/// Besides holding the seed, it drives a small xorshift64 generator so
+/// reproducible `no_std` simulations can draw numbers without threading a
+/// generator through every call. The sequence is deterministic for a given
+/// seed, but not suitable for cryptography.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::GlobalSeed;
+///
+/// static SEED: GlobalSeed = GlobalSeed::new();
+///
+/// assert!(SEED.seed(42));
+/// assert_eq!(SEED.get(), 42);
+/// let roll = SEED.next_u64() % 6 + 1;
+/// assert!((1..=6).contains(&roll));
+/// ```
+#[cfg(target_has_atomic = "64")]
+pub struct GlobalSeed {
+    seed: StaticCell<u64>,
+    state: core::sync::atomic::AtomicU64,
+}
+
+#[cfg(target_has_atomic = "64")]
+impl GlobalSeed {
+    /// Creates an unseeded holder.
+    pub const fn new() -> Self {
+        Self {
+            seed: StaticCell::new(),
+            state: core::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the seed, returning `false` if it was already set.
+    pub fn seed(&self, value: u64) -> bool {
+        self.seed.try_init(value)
+    }
+
+    /// Returns the seed.
+    ///
+    /// # Panics
+    /// Panics if no seed has been set.
+    pub fn get(&self) -> u64 {
+        match self.seed.get() {
+            Some(seed) => *seed,
+            None => panic!("GlobalSeed read before it was seeded"),
+        }
+    }
+
+    /// Advances the shared xorshift64 generator and returns its next value.
+    ///
+    /// # Panics
+    /// Panics if no seed has been set.
+    pub fn next_u64(&self) -> u64 {
+        // xorshift64 never leaves the all-zero state, so substitute a constant
+        let start = match self.get() {
+            0 => 0x9E37_79B9_7F4A_7C15,
+            seed => seed,
+        };
+        let step = |state: u64| {
+            let mut x = if state == 0 { start } else { state };
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        let previous = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |state| Some(step(state)))
+            .unwrap_or_else(|state| state);
+        step(previous)
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl Default for GlobalSeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the next value of a process-wide, monotonically increasing `u64`
+/// sequence.
+///
+/// Handy for correlating log lines or allocations without declaring a
+/// counter. The sequence is shared by every call site and starts at 0.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::unique_id;
+///
+/// let first = unique_id!();
+/// let second = unique_id!();
+/// assert!(second > first);
+/// ```
+#[cfg(target_has_atomic = "64")]
+#[macro_export]
+macro_rules! unique_id {
+    () => {
+        $crate::next_unique_id()
+    };
+}
+
+/// Internal helper backing `unique_id!`.
+#[cfg(target_has_atomic = "64")]
+#[doc(hidden)]
+pub fn next_unique_id() -> u64 {
+    use core::sync::atomic::AtomicU64;
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    // Only uniqueness is required, so no ordering with other memory is needed
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A scope guard that runs a closure when dropped.
+///
+/// Usually created through the [`defer!`] macro.
+pub struct Defer<F: FnMut()> {
+    f: F,
+}
+
+impl<F: FnMut()> Defer<F> {
+    /// Creates a guard that runs `f` when it goes out of scope.
+    #[inline]
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F: FnMut()> Drop for Defer<F> {
+    fn drop(&mut self) {
+        (self.f)();
+    }
+}
+
+/// Runs a closure inside a critical section and returns its value.
+///
+/// The section is entered and left with the functions registered through
+/// [`set_critical_section_impl`]; without a registration the closure just
+/// runs. The exit function also runs if the closure panics. Keep the body
+/// short, as interrupts are typically masked while it runs.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::critical_section;
+///
+/// static mut TICKS: u32 = 0;
+///
+/// let ticks = critical_section!(|| unsafe {
+///     TICKS += 1;
+///     TICKS
+/// });
+/// assert_eq!(ticks, 1);
+/// ```
+#[macro_export]
+macro_rules! critical_section {
+    ($f:expr) => {
+        $crate::_critical_section($f)
+    };
+}
+
+/// Runs a block of code when the enclosing scope exits.
+///
+/// Like Go's `defer`, the block runs on every exit path, including early
+/// `return`s, which makes it handy for releasing hardware resources.
+/// Multiple deferred blocks run in reverse order of declaration.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::defer;
+/// use core::cell::Cell;
+///
+/// let released = Cell::new(false);
+///
+/// fn transfer(released: &Cell<bool>, fail: bool) -> Result<(), ()> {
+///     defer! { released.set(true); }
+///     if fail {
+///         return Err(());
+///     }
+///     Ok(())
+/// }
+///
+/// assert!(transfer(&released, true).is_err());
+/// assert!(released.get());
+/// ```
+#[macro_export]
+macro_rules! defer {
+    ($($body:tt)*) => {
+        let _guard = $crate::Defer::new(|| { $($body)* });
+    };
+}
+
+/// Unwraps an `Option` or `Result`, panicking with the call site and, for
+/// `Err`, the `Debug` form of the error.
+///
+/// `.unwrap()` relies on `#[track_caller]` for its location, which some
+/// embedded panic handlers do not print. `unwrap!` embeds `file!()` and
+/// `line!()` in the panic message itself, so the location survives any
+/// handler that prints the message.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::unwrap;
+///
+/// let digits = unwrap!("0x48".strip_prefix("0x"));
+/// assert_eq!(unwrap!(u8::from_str_radix(digits, 16)), 0x48);
+/// ```
+///
+/// ```rust,should_panic
+/// use noir_macros_core::unwrap;
+///
+/// // panics with "unwrap! failed at <file>:<line>: Err(ParseIntError { kind: PosOverflow })"
+/// let value: u8 = unwrap!("300".parse::<u8>());
+/// ```
+#[macro_export]
+macro_rules! unwrap {
+    ($value:expr $(,)?) => {
+        $crate::Unwrap::unwrap_at($value, file!(), line!())
+    };
+}
+
+/// The values [`unwrap!`] can unwrap.
+#[doc(hidden)]
+pub trait Unwrap {
+    /// The unwrapped value.
+    type Output;
+
+    /// Returns the value, or panics naming `file` and `line`.
+    fn unwrap_at(self, file: &'static str, line: u32) -> Self::Output;
+}
+
+impl<T> Unwrap for Option<T> {
+    type Output = T;
+
+    #[track_caller]
+    fn unwrap_at(self, file: &'static str, line: u32) -> T {
+        match self {
+            Some(value) => value,
+            None => panic!("unwrap! failed at {}:{}: None", file, line),
+        }
+    }
+}
+
+impl<T, E: core::fmt::Debug> Unwrap for Result<T, E> {
+    type Output = T;
+
+    #[track_caller]
+    fn unwrap_at(self, file: &'static str, line: u32) -> T {
+        match self {
+            Ok(value) => value,
+            Err(error) => panic!("unwrap! failed at {}:{}: Err({:?})", file, line, error),
+        }
+    }
+}
+
+/// A setup/teardown pair registered with [`fixture!`].
+#[doc(hidden)]
+#[repr(C)]
+pub struct Fixture {
+    pub setup: fn(),
+    pub teardown: fn(),
+}
+
+// Guarantees the section exists, so its bounds resolve even without fixtures.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+#[link_section = "noir_fixtures"]
+#[used]
+static FIXTURE_SENTINEL: Fixture = Fixture {
+    setup: || {},
+    teardown: || {},
+};
+
+/// Registers a test fixture whose setup and teardown wrap [`with_fixtures`].
+///
+/// Fixtures are collected at link time into a static list, so the set of
+/// fixtures is fixed when the test image is built. This gives `no_std` test
+/// harnesses (for example hardware-in-the-loop tests) a minimal way to bring
+/// hardware into a known state around every test body. Both hooks must be
+/// plain functions or non-capturing closures. Available on ELF targets.
+///
+/// # Examples
+/// ```rust
+/// # #[cfg(target_os = "linux")] {
+/// use noir_macros_core::{fixture, with_fixtures};
+/// use core::sync::atomic::{AtomicBool, Ordering};
+///
+/// static POWERED: AtomicBool = AtomicBool::new(false);
+///
+/// fixture! {
+///     setup: || POWERED.store(true, Ordering::SeqCst),
+///     teardown: || POWERED.store(false, Ordering::SeqCst),
+/// }
+///
+/// with_fixtures(|| assert!(POWERED.load(Ordering::SeqCst)));
+/// assert!(!POWERED.load(Ordering::SeqCst));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! fixture {
+    (setup: $setup:expr, teardown: $teardown:expr $(,)?) => {
+        const _: () = {
+            #[cfg_attr(
+                any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"),
+                link_section = "noir_fixtures"
+            )]
+            #[used]
+            static FIXTURE: $crate::Fixture = $crate::Fixture {
+                setup: $setup,
+                teardown: $teardown,
+            };
+        };
+    };
+}
+
+/// Runs `body` between the setup and teardown of every registered [`fixture!`].
+///
+/// Setups run in link order before the body; teardowns run in reverse order
+/// afterwards, including when the body unwinds.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn with_fixtures<R>(body: impl FnOnce() -> R) -> R {
+    let fixtures = section_entries!(Fixture, "__start_noir_fixtures", "__stop_noir_fixtures");
+
+    for fixture in fixtures {
+        (fixture.setup)();
+    }
+    defer! {
+        for fixture in fixtures.iter().rev() {
+            (fixture.teardown)();
+        }
+    }
+    body()
+}
+
+/// A named event counter declared with [`metric!`].
+#[cfg(target_has_atomic = "64")]
+pub struct Metric {
+    name: &'static str,
+    value: core::sync::atomic::AtomicU64,
+}
+
+#[cfg(target_has_atomic = "64")]
+impl Metric {
+    /// Creates a counter starting at zero.
+    #[doc(hidden)]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: core::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the name the metric was declared with.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Increments the counter by one.
+    #[inline]
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Increments the counter by `n`.
+    #[inline]
+    pub fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the current count.
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(all(
+    target_has_atomic = "64",
+    any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd")
+))]
+static METRIC_SENTINEL: Metric = Metric::new("");
+
+// Guarantees the section exists, so its bounds resolve even without metrics.
+#[cfg(all(
+    target_has_atomic = "64",
+    any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd")
+))]
+#[link_section = "noir_metrics"]
+#[used]
+static METRIC_SENTINEL_ENTRY: &Metric = &METRIC_SENTINEL;
+
+/// Declares a static [`Metric`] counter registered for [`dump_metrics`].
+///
+/// Metrics are collected at link time, so the set of metrics is fixed when
+/// the image is built and no registry storage needs to be sized up front.
+/// Registration is available on ELF targets; elsewhere the counter works
+/// but is not listed.
+///
+/// The counter itself is an ordinary static, initialized like any other
+/// `.data`. Only a `&'static Metric` pointer to it is placed in the
+/// `noir_metrics` link section, so the section is read-only and may live
+/// in flash. Custom linker scripts must keep this section the same way as
+/// `noir_cells`.
+///
+/// # Examples
+/// ```rust
+/// # #[cfg(target_os = "linux")] {
+/// use noir_macros_core::{dump_metrics, metric};
+///
+/// metric!(pub PACKETS_DROPPED);
+///
+/// PACKETS_DROPPED.inc();
+/// PACKETS_DROPPED.add(2);
+///
+/// dump_metrics(|name, value| {
+///     if name == "PACKETS_DROPPED" {
+///         assert_eq!(value, 3);
+///     }
+/// });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! metric {
+    ($vis:vis $name:ident) => {
+        $vis static $name: $crate::Metric = $crate::Metric::new(stringify!($name));
+
+        const _: () = {
+            #[cfg_attr(
+                any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"),
+                link_section = "noir_metrics"
+            )]
+            #[used]
+            static ENTRY: &$crate::Metric = &$name;
+        };
+    };
+}
+
+/// Calls `out` with the name and current value of every [`metric!`], in
+/// link order.
+#[cfg(all(
+    target_has_atomic = "64",
+    any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd")
+))]
+pub fn dump_metrics(mut out: impl FnMut(&str, u64)) {
+    let metrics = section_entries!(&'static Metric, "__start_noir_metrics", "__stop_noir_metrics");
+    for &metric in metrics {
+        if !core::ptr::eq(metric, &METRIC_SENTINEL) {
+            out(metric.name, metric.get());
+        }
+    }
+}
+
+/// A power-on self-test registered with [`self_test!`].
+#[doc(hidden)]
+#[repr(C)]
+pub struct SelfTest {
+    pub name: &'static str,
+    pub run: fn() -> Result<(), &'static str>,
+}
+
+// Guarantees the section exists, so its bounds resolve even without checks.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+#[link_section = "noir_self_tests"]
+#[used]
+static SELF_TEST_SENTINEL: SelfTest = SelfTest {
+    name: "",
+    run: || Ok(()),
+};
+
+/// Defines a power-on self-test function and registers it for
+/// [`run_self_tests`].
+///
+/// Checks are collected at link time like [`fixture!`], so the set of checks
+/// is fixed when the firmware image is built and no registry storage is
+/// allocated at runtime. The function must take no arguments and return
+/// `Result<(), &'static str>`; it stays callable by name. Registration is
+/// available on ELF targets.
+///
+/// # Examples
+/// ```rust
+/// # #[cfg(target_os = "linux")] {
+/// use noir_macros_core::{run_self_tests, self_test};
+///
+/// self_test!(fn check_flash() -> Result<(), &'static str> {
+///     Ok(())
+/// });
+///
+/// run_self_tests(|name, result| {
+///     if name == "check_flash" {
+///         assert_eq!(result, Ok(()));
+///     }
+/// });
+/// # }
+/// ```
+#[macro_export]
+macro_rules! self_test {
+    ($(#[$meta:meta])* $vis:vis fn $name:ident() -> $ret:ty $body:block) => {
+        $(#[$meta])*
+        $vis fn $name() -> $ret $body
+
+        const _: () = {
+            #[cfg_attr(
+                any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"),
+                link_section = "noir_self_tests"
+            )]
+            #[used]
+            static SELF_TEST: $crate::SelfTest = $crate::SelfTest {
+                name: stringify!($name),
+                run: $name,
+            };
+        };
+    };
+}
+
+/// Runs every [`self_test!`] in link order, calling `report` with each
+/// check's name and outcome.
+///
+/// All checks run even if an earlier one fails, so the caller sees every
+/// fault at power-on.
+#[cfg(any(target_os = "none", target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn run_self_tests(mut report: impl FnMut(&str, Result<(), &str>)) {
+    let tests = section_entries!(SelfTest, "__start_noir_self_tests", "__stop_noir_self_tests");
+    for test in tests {
+        if !core::ptr::eq(test, &SELF_TEST_SENTINEL) {
+            report(test.name, (test.run)());
+        }
+    }
+}
+
+/// A [`GlobalAlloc`](core::alloc::GlobalAlloc) wrapper counting the
+/// allocations made through an inner allocator.
+///
+/// Install it as the `#[global_allocator]` around the allocator the program
+/// already uses, then query [`stats`](Self::stats) to find leaks or size
+/// the heap. The counters are plain atomics updated on every call.
+///
+/// Only available with the `alloc-stats` feature.
+///
+/// # Examples
+/// ```rust,ignore
+/// use noir_macros_core::AllocStats;
+/// use std::alloc::System;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: AllocStats<System> = AllocStats::new(System);
+///
+/// let (live, peak, count) = ALLOCATOR.stats();
+/// ```
+#[cfg(feature = "alloc-stats")]
+pub struct AllocStats<A> {
+    inner: A,
+    live: AtomicUsize,
+    peak: AtomicUsize,
+    count: AtomicUsize,
+}
+
+#[cfg(feature = "alloc-stats")]
+impl<A> AllocStats<A> {
+    /// Wraps `inner` with all counters at zero.
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            live: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the live bytes, the peak of live bytes, and the number of
+    /// allocations made so far. A successful `realloc` counts as one
+    /// allocation.
+    pub fn stats(&self) -> (usize, usize, usize) {
+        (
+            self.live.load(Ordering::Relaxed),
+            self.peak.load(Ordering::Relaxed),
+            self.count.load(Ordering::Relaxed),
+        )
+    }
+
+    fn record_alloc(&self, size: usize) {
+        let live = self.live.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(live, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: every call is forwarded to the inner allocator unchanged
+#[cfg(feature = "alloc-stats")]
+unsafe impl<A: core::alloc::GlobalAlloc> core::alloc::GlobalAlloc for AllocStats<A> {
+    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        // SAFETY: the caller upholds the `GlobalAlloc` contract
+        let ptr = unsafe { self.inner.alloc(layout) };
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: core::alloc::Layout) -> *mut u8 {
+        // SAFETY: the caller upholds the `GlobalAlloc` contract
+        let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        // SAFETY: the caller upholds the `GlobalAlloc` contract
+        unsafe { self.inner.dealloc(ptr, layout) };
+        self.live.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: core::alloc::Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: the caller upholds the `GlobalAlloc` contract
+        let new_ptr = unsafe { self.inner.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            self.live.fetch_sub(layout.size(), Ordering::Relaxed);
+            self.record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Initializes a [`StaticCell`] with the one backend selected at build time.
+///
+/// Each arm pairs a `cfg` predicate, usually `feature = "..."`, with the
+/// expression producing that backend. Exactly one predicate must hold:
+/// building with none or with several of them active fails compilation, so
+/// mutually exclusive Cargo features cannot be combined by accident. The
+/// macro evaluates to the result of `try_init` on the cell.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{select_backend, StaticCell};
+///
+/// trait Storage: Sync {
+///     fn name(&self) -> &'static str;
+/// }
+///
+/// struct Flash;
+/// impl Storage for Flash {
+///     fn name(&self) -> &'static str { "flash" }
+/// }
+///
+/// struct Ram;
+/// impl Storage for Ram {
+///     fn name(&self) -> &'static str { "ram" }
+/// }
+///
+/// static STORAGE: StaticCell<&'static dyn Storage> = StaticCell::new();
+///
+/// select_backend!(STORAGE, {
+///     feature = "flash-storage" => &Flash,
+///     not(feature = "flash-storage") => &Ram,
+/// });
+/// assert_eq!(STORAGE.get().unwrap().name(), "ram");
+/// ```
+///
+/// No active backend is a compile error:
+/// ```rust,compile_fail
+/// use noir_macros_core::{select_backend, StaticCell};
+///
+/// static VALUE: StaticCell<u8> = StaticCell::new();
+/// select_backend!(VALUE, { feature = "backend-a" => 1, feature = "backend-b" => 2 });
+/// ```
+///
+/// So is more than one:
+/// ```rust,compile_fail
+/// use noir_macros_core::{select_backend, StaticCell};
+///
+/// static VALUE: StaticCell<u8> = StaticCell::new();
+/// select_backend!(VALUE, { not(feature = "backend-a") => 1, not(feature = "backend-b") => 2 });
+/// ```
+#[macro_export]
+macro_rules! select_backend {
+    ($cell:expr, { $($predicate:meta => $backend:expr),+ $(,)? }) => {{
+        #[cfg(not(any($($predicate),+)))]
+        compile_error!(concat!(
+            "select_backend!: no backend selected; enable exactly one of:",
+            $(" `", stringify!($predicate), "`"),+
+        ));
+
+        const _: () = assert!(
+            0 $(+ cfg!($predicate) as usize)+ <= 1,
+            "select_backend!: more than one backend selected"
+        );
+
+        #[allow(unreachable_code)]
+        let initialized = 'select: {
+            $(
+                #[cfg($predicate)]
+                break 'select $cell.try_init($backend);
+            )+
+            false
+        };
+        initialized
+    }};
+}
+
+/// Verifies the size of a type at compile time.
+/// 
+/// # Understanding Type Size
+/// In systems programming, the exact size of types is crucial for:
+/// - Memory layout control
+/// - FFI (Foreign Function Interface) compatibility
+/// - Embedded systems constraints
+/// - Performance optimization
+/// 
+/// # Memory Alignment
+/// ```text
+/// struct Example {    Alignment Padding
+/// ┌─────────────┐    ┌─┐
+/// │  u32 (4B)   │    │ │
+/// ├─────────────┤    ├─┤
+/// │  u8 (1B)    │ -> │X│ <- 3 bytes padding
+/// ├─────────────┤    ├─┤
+/// │  u32 (4B)   │    │ │
+/// └─────────────┘    └─┘
+/// Total: 12 bytes
+/// ```
+/// 
+/// # Usage Examples
+/// ```rust
+/// use noir_macros_core::const_assert_size;
+/// // Basic size check
+/// const_assert_size!(u32, 4);
+/// 
+/// // Custom struct size verification
+/// #[repr(C)]
+/// struct Packet {
+///     header: u32,    // 4 bytes
+///     flags: u8,      // 1 byte
+///     _pad: [u8; 3],  // 3 bytes padding
+///     data: u32,      // 4 bytes
+/// }
+/// const_assert_size!(Packet, 12);
+/// ```
+/// 
+/// # Common Applications
+/// 1. Network protocol structures
+/// 2. Hardware interface types
+/// 3. Memory-mapped I/O
+/// 4. Binary file formats
+/// 
+/// # Best Practices
+/// 1. Always use with `#[repr(C)]` for predictable layout
+/// 2. Account for padding in size calculations
+/// 3. Document size assumptions
+/// 4. Use with alignment assertions
+#[macro_export]
+macro_rules! const_assert_size {
+    ($type:ty, $size:expr) => {
+        const _: () = assert!(core::mem::size_of::<$type>() == $size);
+    };
+}
+
+/// Verifies the alignment of a type at compile time.
+/// 
+/// # Understanding Alignment
+/// Memory alignment is crucial for:
+/// - CPU access efficiency
+/// - Hardware requirements
+/// - Platform compatibility
+/// - Performance optimization
+/// 
+/// # Alignment Visualization
+/// ```text
+/// Memory Address: 0  1  2  3  4  5  6  7
+///                ┌──┬──┬──┬──┬──┬──┬──┬──┐
+/// Aligned(4):    │  u32   │  u32   │  u32 
+///                └──┴──┴──┴──┴──┴──┴──┴──┘
+///                ↑     ↑     ↑     ↑
+///                Valid addresses for u32 (4-byte aligned)
+/// ```
+/// 
+/// # Usage Examples
+/// ```rust
+/// use noir_macros_core::const_assert_align;
+/// 
+/// // Basic alignment checks
+/// const_assert_align!(u32, 4);
+/// const_assert_align!(u64, 8);
+/// 
+/// // Custom aligned types
+/// #[repr(align(16))]
+/// struct SimdVector {
+///     data: [f32; 4],
+/// }
+/// const_assert_align!(SimdVector, 16);
+/// ```
+/// 
+/// # Common Use Cases
+/// 1. SIMD data structures
+/// 2. DMA buffers
+/// 3. Hardware interfaces
+/// 4. Cache-line optimization
+/// 
+/// # Best Practices
+/// 1. Use `#[repr(align(N))]` for custom alignment
+/// 2. Consider cache line sizes (usually 64 bytes)
+/// 3. Document alignment requirements
+/// 4. Pair with size assertions
+#[macro_export]
+macro_rules! const_assert_align {
+    ($type:ty, $align:expr) => {
+        const _: () = assert!(core::mem::align_of::<$type>() == $align);
+    };
+}
+
+/// Verifies that a type implements the given traits at compile time.
+///
+/// Locks in guarantees such as a public type staying `Send`, `Sync` or
+/// `Copy`: a change that drops the impl fails to compile at the assertion
+/// instead of in downstream code. Complements [`const_assert_size!`] and
+/// [`const_assert_align!`].
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{static_assert_impl, SpinLock};
+///
+/// static_assert_impl!(SpinLock<u32>: Send + Sync);
+/// static_assert_impl!(str: core::fmt::Display);
+/// ```
+///
+/// Unsatisfied bounds are rejected:
+/// ```rust,compile_fail
+/// use noir_macros_core::static_assert_impl;
+///
+/// static_assert_impl!(core::cell::Cell<u32>: Sync);
+/// ```
+#[macro_export]
+macro_rules! static_assert_impl {
+    ($type:ty: $($bound:tt)+) => {
+        const _: () = {
+            fn assert_impl<T: ?Sized + $($bound)+>() {}
+            let _ = assert_impl::<$type>;
+        };
+    };
+}
+
+/// Verifies the complete layout of a `#[repr(C)]` struct at compile time.
+///
+/// Consolidates size, alignment and per-field offset checks into one
+/// declarative block per FFI type. Each listed field is also checked to
+/// have the stated type; field types spanning several tokens, such as
+/// `Option<u32>` or `core::ffi::c_int`, must be wrapped in parentheses.
+///
+/// # Usage Examples
+/// ```rust
+/// use noir_macros_core::assert_layout;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     header: u32,
+///     flags: u8,
+///     data: u32,
+/// }
+///
+/// assert_layout! {
+///     struct Packet {
+///         header: u32 @ 0,
+///         flags: u8 @ 4,
+///         data: u32 @ 8,
+///     },
+///     size = 12,
+///     align = 4
+/// }
+/// ```
+///
+/// A wrong offset fails compilation:
+/// ```rust,compile_fail
+/// use noir_macros_core::assert_layout;
+///
+/// #[repr(C)]
+/// struct Packet {
+///     header: u32,
+///     flags: u8,
+///     data: u32,
+/// }
+///
+/// assert_layout! {
+///     struct Packet { header: u32 @ 0, flags: u8 @ 4, data: u32 @ 5 },
+///     size = 12,
+///     align = 4
+/// }
+/// ```
+///
+/// # Best Practices
+/// 1. Keep the block next to the struct definition
+/// 2. List every field so reordering is caught
+/// 3. Pair with `#[repr(C)]` for a stable layout
+#[macro_export]
+macro_rules! assert_layout {
+    (
+        struct $type:ty {
+            $($field:ident: $field_type:tt @ $offset:expr),* $(,)?
+        },
+        size = $size:expr,
+        align = $align:expr $(,)?
+    ) => {
+        const _: () = assert!(core::mem::size_of::<$type>() == $size);
+        const _: () = assert!(core::mem::align_of::<$type>() == $align);
+        $(
+            const _: () = assert!(core::mem::offset_of!($type, $field) == $offset);
+            const _: fn(&$type) -> &$field_type = |value| &value.$field;
+        )*
+    };
+}
+
+/// Rounds `value` up to the next multiple of `align`.
+///
+/// `align` must be a power of two; this is checked in debug builds.
+///
+/// # Panics
+/// Panics if the rounded value does not fit in a `usize`. In a `const`
+/// context this is a compile error instead.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::align_up;
+///
+/// const DMA_START: usize = align_up(0x2000_0013, 32);
+/// assert_eq!(DMA_START, 0x2000_0020);
+/// assert_eq!(align_up(64, 32), 64);
+/// ```
+#[inline]
+pub const fn align_up(value: usize, align: usize) -> usize {
+    debug_assert!(align.is_power_of_two(), "alignment must be a power of two");
+    match value.checked_add(align - 1) {
+        Some(end) => end & !(align - 1),
+        None => panic!("align_up overflowed usize"),
+    }
+}
+
+/// Rounds `value` down to the previous multiple of `align`.
+///
+/// `align` must be a power of two; this is checked in debug builds.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::align_down;
+///
+/// assert_eq!(align_down(0x1fff, 0x1000), 0x1000);
+/// assert_eq!(align_down(0x2000, 0x1000), 0x2000);
+/// ```
+#[inline]
+pub const fn align_down(value: usize, align: usize) -> usize {
+    debug_assert!(align.is_power_of_two(), "alignment must be a power of two");
+    value & !(align - 1)
+}
+
+/// Creates a compile-time string literal.
+/// 
+/// # Understanding Const Strings
+/// Const strings are string literals that are:
+/// - Evaluated at compile time
+/// - Stored in the binary
+/// - Zero runtime overhead
+/// - Type checked at compile time
+/// 
+/// # Memory Layout
+/// ```text
+/// Static String in Binary:
+/// ┌────────────────────┐
+/// │ Length (usize)     │ <- Known at compile time
+/// ├────────────────────┤
+/// │ UTF-8 Bytes        │ <- Immutable data
+/// ├────────────────────┤
+/// │ NUL terminator     │ <- For C compatibility
+/// └────────────────────┘
+/// ```
+/// 
+/// # Usage Examples
+/// ```rust
+/// use noir_macros_core::const_str;
+///
+/// // Basic usage
+/// const GREETING: &str = const_str!("Hello, World!");
+/// 
+/// // In static contexts
+/// static APP_NAME: &str = const_str!("MyApp");
+/// 
+/// // With escape sequences
+/// const PATH: &str = const_str!("C:\\Program Files\\App");
+/// ```
+/// 
+/// # Common Applications
+/// 1. Error messages
+/// 2. Configuration strings
+/// 3. Static resources
+/// 4. Compile-time constants
+/// 
+/// # Best Practices
+/// 1. Use for truly constant strings
+/// 2. Consider UTF-8 implications
+/// 3. Document string purpose
+/// 4. Prefer over string literals for constants
+#[macro_export]
+macro_rules! const_str {
+    ($s:expr) => { $s };
+}
+
+/// Compares two constant strings for equality at compile time.
+///
+/// `==` on `&str` is not usable in const contexts; this macro evaluates the
+/// comparison with [`str_eq`] as a `bool` constant, e.g. for compile-time
+/// assertions on strings from `env!`.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::const_str_eq;
+///
+/// const MODE: &str = "release";
+/// const _: () = assert!(const_str_eq!(MODE, "release"));
+///
+/// assert!(!const_str_eq!(MODE, "debug"));
+/// ```
+#[macro_export]
+macro_rules! const_str_eq {
+    ($a:expr, $b:expr) => {{
+        const EQ: bool = $crate::str_eq($a, $b);
+        EQ
+    }};
+}
+
+/// Returns the larger of two integer expressions, usable in const contexts.
+///
+/// Handy for layout computations such as sizing a buffer for the larger of
+/// two types. Each argument is evaluated once.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::const_max;
+///
+/// const SCRATCH: usize = const_max!(core::mem::size_of::<u64>(), core::mem::size_of::<[u8; 12]>());
+/// assert_eq!(SCRATCH, 12);
+/// ```
+#[macro_export]
+macro_rules! const_max {
+    ($a:expr, $b:expr) => {{
+        let (a, b) = ($a, $b);
+        if a >= b { a } else { b }
+    }};
+}
+
+/// Returns the smaller of two integer expressions, usable in const contexts.
+///
+/// Each argument is evaluated once.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::const_min;
+///
+/// const CHUNK: usize = const_min!(4096, 1500);
+/// assert_eq!(CHUNK, 1500);
+/// ```
+#[macro_export]
+macro_rules! const_min {
+    ($a:expr, $b:expr) => {{
+        let (a, b) = ($a, $b);
+        if a <= b { a } else { b }
+    }};
+}
+
+/// Defines a fieldless enum together with a const `as_str` returning each
+/// variant's name.
+///
+/// A lighter alternative to `#[derive(Debug)]` for logging enum values:
+/// the names are plain `&'static str` constants and the lookup is a
+/// `match`, usable in const contexts. Attributes and explicit
+/// discriminants are passed through.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::enum_names;
+///
+/// enum_names! {
+///     #[derive(Clone, Copy, PartialEq)]
+///     pub enum State {
+///         Idle,
+///         Running,
+///         Done = 10,
+///     }
+/// }
+///
+/// const NAME: &str = State::Done.as_str();
+/// assert_eq!(NAME, "Done");
+/// assert_eq!(State::Idle.as_str(), "Idle");
+/// ```
+#[macro_export]
+macro_rules! enum_names {
+    (
+        $(#[$outer:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$inner:meta])*
+                $variant:ident $(= $discriminant:expr)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$outer])*
+        $vis enum $name {
+            $(
+                $(#[$inner])*
+                $variant $(= $discriminant)?,
+            )*
+        }
+
+        impl $name {
+            /// Returns the name of the variant.
+            #[allow(dead_code)]
+            $vis const fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => stringify!($variant),)*
+                }
+            }
+        }
+    };
+}
+
+/// Bit-counting helpers that work for any integer type in const contexts.
+///
+/// Each form forwards to the inherent method of the argument's type, so
+/// generic flag and bitset code can be written once as a macro body:
+///
+/// - `bits!(count_ones(x))`, `bits!(leading_zeros(x))` and
+///   `bits!(trailing_zeros(x))` return a `u32`;
+/// - `bits!(bit_index(x))` returns `Some(position)` if `x` has exactly one
+///   bit set, and `None` otherwise.
+///
+/// The argument is evaluated once.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::bits;
+///
+/// const IRQ_UART: u16 = 1 << 9;
+/// const UART_LINE: u32 = match bits!(bit_index(IRQ_UART)) {
+///     Some(index) => index,
+///     None => panic!("IRQ_UART must be a single bit"),
+/// };
+///
+/// assert_eq!(UART_LINE, 9);
+/// assert_eq!(bits!(count_ones(0b1011_u8)), 3);
+/// assert_eq!(bits!(leading_zeros(IRQ_UART)), 6);
+/// assert_eq!(bits!(bit_index(0b110_u64)), None);
+/// ```
+#[macro_export]
+macro_rules! bits {
+    (count_ones($value:expr)) => {
+        ($value).count_ones()
+    };
+    (leading_zeros($value:expr)) => {
+        ($value).leading_zeros()
+    };
+    (trailing_zeros($value:expr)) => {
+        ($value).trailing_zeros()
+    };
+    (bit_index($value:expr)) => {{
+        let value = $value;
+        if value.count_ones() == 1 {
+            Some(value.trailing_zeros())
+        } else {
+            None
+        }
+    }};
+}
+
+/// Returns true if `a` and `b` contain the same bytes.
+pub const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Builds a lookup table at compile time by applying a `const fn` to each index.
+///
+/// `const_table!(N, f)` evaluates to `[f(0), f(1), ..., f(N - 1)]`, where `f`
+/// is a path to a `const fn(usize) -> T` with `T: Copy`. Closures are not
+/// allowed in const contexts, so a named function is required. Typical uses
+/// are CRC tables, gamma correction curves and other LUTs.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::const_table;
+///
+/// const fn crc8_entry(index: usize) -> u8 {
+///     let mut crc = index as u8;
+///     let mut bit = 0;
+///     while bit < 8 {
+///         crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+///         bit += 1;
+///     }
+///     crc
+/// }
+///
+/// const CRC8_TABLE: [u8; 256] = const_table!(256, crc8_entry);
+/// assert_eq!(CRC8_TABLE[1], 0x07);
+/// ```
+#[macro_export]
+macro_rules! const_table {
+    ($len:expr, $f:path) => {{
+        let mut table = [$f(0); $len];
+        let mut index = 1;
+        while index < $len {
+            table[index] = $f(index);
+            index += 1;
+        }
+        table
+    }};
+}
+
+/// Decodes a base64 string literal into a `&'static [u8]` at compile time.
+///
+/// Useful for embedding binary keys or certificates that are given as base64
+/// without shipping the encoded form or running a build script. The standard
+/// alphabet (`A-Z`, `a-z`, `0-9`, `+`, `/`) with `=` padding is accepted;
+/// anything else fails compilation.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::const_base64;
+///
+/// const KEY: &[u8] = const_base64!("3q2+7w==");
+/// assert_eq!(KEY, &[0xde, 0xad, 0xbe, 0xef]);
+///
+/// const TEXT: &[u8] = const_base64!("bm9pcg==");
+/// assert_eq!(TEXT, b"noir");
+/// ```
+///
+/// Invalid input is rejected at compile time:
+/// ```rust,compile_fail
+/// use noir_macros_core::const_base64;
+///
+/// const BAD: &[u8] = const_base64!("bm9p*g==");
+/// ```
+#[macro_export]
+macro_rules! const_base64 {
+    ($s:expr) => {{
+        const INPUT: &[u8] = $s.as_bytes();
+        const OUTPUT: [u8; $crate::base64_decoded_len(INPUT)] = $crate::base64_decode(INPUT);
+        &OUTPUT as &'static [u8]
+    }};
+}
+
+/// Maps a base64 alphabet character to its 6-bit value.
+const fn base64_value(c: u8) -> u8 {
+    match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => panic!("invalid base64 character"),
+    }
+}
+
+/// Returns the number of bytes `input` decodes to.
+///
+/// # Panics
+/// Panics (or fails const evaluation) if the length is not a multiple of 4.
+pub const fn base64_decoded_len(input: &[u8]) -> usize {
+    let len = input.len();
+    assert!(len % 4 == 0, "base64 input length must be a multiple of 4");
+    let mut padding = 0;
+    if len > 0 && input[len - 1] == b'=' {
+        padding += 1;
+        if input[len - 2] == b'=' {
+            padding += 1;
+        }
+    }
+    len / 4 * 3 - padding
+}
+
+/// Decodes base64 `input` into an array of exactly `N` bytes.
+///
+/// # Panics
+/// Panics (or fails const evaluation) on characters outside the alphabet,
+/// misplaced padding, or if `N` differs from [`base64_decoded_len`].
+pub const fn base64_decode<const N: usize>(input: &[u8]) -> [u8; N] {
+    assert!(base64_decoded_len(input) == N, "output length does not match decoded length");
+    let mut out = [0u8; N];
+    let mut i = 0;
+    let mut o = 0;
+    while i < input.len() {
+        let last = i + 4 == input.len();
+        let pad3 = last && input[i + 3] == b'=';
+        let pad2 = last && input[i + 2] == b'=';
+        assert!(!pad2 || pad3, "invalid base64 padding");
+
+        let a = base64_value(input[i]);
+        let b = base64_value(input[i + 1]);
+        let c = if pad2 { 0 } else { base64_value(input[i + 2]) };
+        let d = if pad3 { 0 } else { base64_value(input[i + 3]) };
+
+        out[o] = (a << 2) | (b >> 4);
+        if !pad2 {
+            out[o + 1] = (b << 4) | (c >> 2);
+        }
+        if !pad3 {
+            out[o + 2] = (c << 6) | d;
+        }
+        i += 4;
+        o += 3;
+    }
+    out
+}
+
+/// Parses a string literal into an integer constant at compile time.
+///
+/// `str::parse` is not `const`, so config values arriving as strings (e.g.
+/// from `env!`) cannot otherwise become typed constants. Decimal and
+/// `0x`-prefixed hexadecimal input is accepted, with an optional leading `-`
+/// for signed types and `_` digit separators. Invalid digits and values that
+/// do not fit the target type fail compilation.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::parse_int;
+///
+/// const STACK_SIZE: u32 = parse_int!("4096", u32);
+/// const BASE: usize = parse_int!("0x2000_0000", usize);
+/// const OFFSET: i8 = parse_int!("-128", i8);
+/// assert_eq!(STACK_SIZE, 4096);
+/// assert_eq!(BASE, 0x2000_0000);
+/// assert_eq!(OFFSET, i8::MIN);
+/// ```
+///
+/// Out-of-range values are rejected at compile time:
+/// ```rust,compile_fail
+/// use noir_macros_core::parse_int;
+///
+/// const TOO_BIG: u8 = parse_int!("256", u8);
+/// ```
+#[macro_export]
+macro_rules! parse_int {
+    ($s:expr, $ty:ty) => {{
+        const VALUE: $ty = {
+            let (negative, magnitude) = $crate::parse_int_literal(
+                $s,
+                <$ty>::MAX as u128,
+                (<$ty>::MIN as i128).unsigned_abs(),
+            );
+            if negative {
+                (0 as $ty).wrapping_sub(magnitude as $ty)
+            } else {
+                magnitude as $ty
+            }
+        };
+        VALUE
+    }};
+}
+
+/// Parses an integer literal into its sign and magnitude.
+///
+/// Used by [`parse_int!`]; `max_positive` and `max_negative` bound the
+/// magnitude of non-negative and negative values respectively.
+///
+/// # Panics
+/// Panics (or fails const evaluation) on invalid digits or out-of-range values.
+#[doc(hidden)]
+pub const fn parse_int_literal(text: &str, max_positive: u128, max_negative: u128) -> (bool, u128) {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    let negative = !bytes.is_empty() && bytes[0] == b'-';
+    if negative {
+        i = 1;
+    }
+    let mut radix = 10;
+    if bytes.len() >= i + 2 && bytes[i] == b'0' && (bytes[i + 1] == b'x' || bytes[i + 1] == b'X') {
+        radix = 16;
+        i += 2;
+    }
+
+    let mut magnitude: u128 = 0;
+    let mut digits = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        i += 1;
+        if c == b'_' {
+            continue;
+        }
+        digits += 1;
+        let digit = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' if radix == 16 => c - b'a' + 10,
+            b'A'..=b'F' if radix == 16 => c - b'A' + 10,
+            _ => panic!("invalid digit in integer literal"),
+        };
+        magnitude = match magnitude.checked_mul(radix) {
+            Some(shifted) => match shifted.checked_add(digit as u128) {
+                Some(value) => value,
+                None => panic!("integer literal out of range"),
+            },
+            None => panic!("integer literal out of range"),
+        };
+    }
+    assert!(digits > 0, "integer literal has no digits");
+
+    let max = if negative { max_negative } else { max_positive };
+    assert!(magnitude <= max, "integer literal out of range");
+    (negative, magnitude)
+}
+
+/// Computes the 64-bit FNV-1a hash of a string literal at compile time.
+///
+/// Useful as a compact string ID or for dispatching on hashed keys without
+/// hashing the candidates at runtime. Match patterns cannot contain macro
+/// calls that evaluate expressions, so bind the hashes to constants first.
+///
+/// FNV-1a is fast but not collision resistant: distinct keys can share a
+/// hash, and inputs chosen by an attacker can collide on purpose. Check a
+/// fixed key set for collisions (e.g. with a test) before relying on it, and
+/// compare the full key after a match when inputs are untrusted.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{const_fnv1a, fnv1a_64};
+///
+/// const START: u64 = const_fnv1a!("start");
+/// const STOP: u64 = const_fnv1a!("stop");
+///
+/// fn dispatch(command: &str) -> u8 {
+///     match fnv1a_64(command.as_bytes()) {
+///         START => 1,
+///         STOP => 2,
+///         _ => 0,
+///     }
+/// }
+///
+/// assert_eq!(dispatch("stop"), 2);
+/// assert_eq!(dispatch("reset"), 0);
+/// ```
+#[macro_export]
+macro_rules! const_fnv1a {
+    ($s:expr) => {{
+        const HASH: u64 = $crate::fnv1a_64($s.as_bytes());
+        HASH
+    }};
+}
+
+/// Returns the 64-bit FNV-1a hash of `bytes`.
+pub const fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// Performs compile-time type checks and assertions.
+/// 
+/// # Understanding Type Checks
+/// Type checking at compile time ensures:
+/// - Memory safety through layout verification
+/// - Size and alignment constraints
+/// - Value semantics validation
+/// - Performance characteristics
+/// 
+/// # Type Properties Verified
+/// ```text
+/// Type Requirements:
+/// ┌──────────────────┐
+/// │ POD Status       │ No custom Drop impl
+/// ├──────────────────┤
+/// │ Size Limits      │ Memory boundaries
+/// ├──────────────────┤
+/// │ Alignment        │ Memory layout
+/// └──────────────────┘
+/// ```
+/// 
+/// # Usage Examples
+/// ```rust
+/// use noir_macros_core::type_check;
+/// 
+/// #[repr(C)]
+/// struct SafeType {
+///     data: u32,
+/// }
+/// 
+/// type_check! {
+///     ensure SafeType: {
+///         is_pod,                // Must be Plain Old Data
+///         max_size: 4,          // No larger than 4 bytes
+///         aligned_to: 4         // Must be 4-byte aligned
+///     }
+/// }
+/// ```
+/// 
+/// # Available Checks
+/// 1. `is_pod` - Ensures type has no custom Drop implementation
+/// 2. `max_size: N` - Verifies type size ≤ N bytes
+/// 3. `aligned_to: N` - Confirms type has N-byte alignment
+/// 
+/// # Best Practices
+/// 1. Use in safety-critical code
+/// 2. Document check rationale
+/// 3. Group related checks
+/// 4. Add error messages
+/// 
+/// # Implementation Details
+/// The checks are implemented using Rust's const evaluation system,
+/// ensuring all verifications happen at compile time with zero
+/// runtime overhead.
+#[macro_export]
+macro_rules! type_check {
+    (ensure $type:ty: { is_pod $(,)? }) => {
+        const _: () = assert!(core::mem::needs_drop::<$type>() == false);
+    };
+    (ensure $type:ty: { max_size: $size:expr $(,)? }) => {
+        const _: () = assert!(core::mem::size_of::<$type>() <= $size);
+    };
+    (ensure $type:ty: { aligned_to: $align:expr $(,)? }) => {
+        const _: () = assert!(core::mem::align_of::<$type>() == $align);
+    };
+    (ensure $type:ty: { $($check:ident $(: $val:expr)? ),+ $(,)? }) => {
+        $($crate::type_check!(ensure $type: { $check $(: $val)? });)+
+    };
+}
+
+/// Creates a new vector with the given elements.
+/// 
+/// # Understanding Vectors
+/// A vector is a dynamic array that can grow or shrink in size. It's one of the most
+/// commonly used collection types in Rust because it provides:
+/// - Dynamic sizing (can grow/shrink)
+/// - Contiguous memory storage (fast access)
+/// - Automatic memory management
+/// 
+/// # Memory Layout
+/// ```text
+/// Vec<T>
+/// ┌─────────┬─────────┬──────────┐
+/// │ pointer │capacity │  length  │ (on stack)
+/// └───┬─────┴─────────┴──────────┘
+///     │
+///     v
+/// ┌───┬───┬───┬───┬─────┐
+/// │ 0 │ 1 │ 2 │ 3 │ ... │ (on heap)
+/// └───┴───┴───┴───┴─────┘
+/// ```
+/// 
+/// # Usage Patterns
+/// The macro builds a [`noir_macros_core::Vec`](Vec), a re-export of
+/// `alloc::vec::Vec`, so it works without importing anything from `alloc`.
+/// Name the type through the same re-export to avoid ambiguity when both
+/// `std` and this crate's `vec!` are in scope.
+///
+/// 1. Empty vector:
+/// ```rust
+/// use noir_macros_core::{vec, Vec};
+/// 
+/// let v: Vec<i32> = vec![];
+/// ```
+/// 
+/// 2. Vector with repeated elements:
+/// ```rust
+/// // Creates [1, 1, 1, 1, 1]
+/// let v = vec![1; 5];
+/// ```
+/// 
+/// 3. Vector with specific elements:
+/// ```rust
+/// let v = vec![1, 2, 3, 4, 5];
+/// ```
+/// 
+/// # Performance Considerations
+/// - Initial allocation happens on the heap
+/// - Capacity doubles when more space is needed
+/// - Consider pre-allocating with known size
+/// 
+/// # Best Practices
+/// 1. Use `with_capacity` when size is known
+/// 2. Clear with `clear()` instead of reassigning
+/// 3. Use `drain()` to remove and reuse elements
+/// 4. Consider `Vec::new()` for empty vectors
+#[macro_export]
+macro_rules! vec {
+    () => {
+        $crate::Vec::new()
+    };
+    ($elem:expr; $n:expr) => {
+        ::core::iter::Iterator::collect::<$crate::Vec<_>>(::core::iter::Iterator::take(::core::iter::repeat($elem), $n))
+    };
+    ($($x:expr),+ $(,)?) => {
+        $crate::Vec::from([$($x),+])
+    };
+}
+
+/// Creates a fixed-size array with the given elements.
+/// 
+/// # Understanding Arrays
+/// Arrays in Rust are fixed-size sequences of elements stored in contiguous memory.
+/// Unlike vectors, their size is part of their type and cannot change.
+/// 
+/// # Key Characteristics
+/// - Fixed size known at compile time
+/// - Stored entirely on the stack
+/// - Zero runtime overhead
+/// - Direct indexing without bounds checking
+/// 
+/// # Memory Layout
+/// ```text
+/// [T; N] (on stack)
+/// ┌───┬───┬───┬───┐
+/// │ 0 │ 1 │ 2 │ 3 │
+/// └───┴───┴───┴───┘
+/// ```
+/// 
+/// # Usage Examples
+/// ```rust
+/// use noir_macros_core::array;
+///
+/// // Empty array
+/// let empty: [i32; 0] = array![];
+/// 
+/// // Array with values
+/// let numbers = array![1, 2, 3, 4];
+/// 
+/// // Note: All elements must be of the same type
+/// // This would NOT work:
+/// // let mixed = array![1, 2.5, 3.7];  // Error: mixed types
+/// // for mixed types, use a tuple
+/// let mixed: (i32, f64) = (1, 2.5);
+/// ```
+/// 
+/// # Common Use Cases
+/// 1. Fixed-size data structures
+/// 2. Performance-critical code
+/// 3. Embedded systems
+/// 4. Stack-only allocations
+/// 
+/// # When to Use Arrays
+/// 1. Fixed-size data structures
+/// 2. Performance-critical code
+/// 3. Embedded systems
+/// 4. Stack-only allocations
+/// 
+/// # Best Practices
+/// 1. Use when size is known at compile time
+/// 2. Consider for small, fixed collections
+/// 3. Use with SIMD operations
+/// 4. Prefer over Vec for tiny sequences
+#[macro_export]
+macro_rules! array {
+    () => { [] };
+    ($($x:expr),+ $(,)?) => { [$($x),+] };
+}
+
+#[cfg(feature = "heapless")]
+#[doc(hidden)]
+pub use heapless;
+
+/// Creates a fixed-capacity `heapless::Vec` with the given elements.
+///
+/// This mirrors `vec!` for bounded, allocation-free vectors: the capacity
+/// comes first, followed by either an element list or a repeat expression.
+/// Requires the `heapless` feature.
+///
+/// # Panics
+/// Panics if the number of elements exceeds the capacity.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::hvec;
+///
+/// // Empty vector with room for 8 elements
+/// let empty: heapless::Vec<u8, 8> = hvec![8];
+/// assert!(empty.is_empty());
+///
+/// // Element list
+/// let v = hvec![4; 1, 2, 3];
+/// assert_eq!(v.as_slice(), &[1, 2, 3]);
+/// assert_eq!(v.capacity(), 4);
+///
+/// // Repeated element
+/// let zeros = hvec![16; 0u8; 10];
+/// assert_eq!(zeros.len(), 10);
+/// ```
+#[cfg(feature = "heapless")]
+#[macro_export]
+macro_rules! hvec {
+    ($cap:expr $(;)?) => {
+        $crate::heapless::Vec::<_, $cap>::new()
+    };
+    ($cap:expr; $elem:expr; $n:expr) => {{
+        let mut v = $crate::heapless::Vec::<_, $cap>::new();
+        if v.resize($n, $elem).is_err() {
+            panic!("hvec! element count exceeds capacity");
+        }
+        v
+    }};
+    ($cap:expr; $($x:expr),+ $(,)?) => {{
+        let mut v = $crate::heapless::Vec::<_, $cap>::new();
+        $(
+            if v.push($x).is_err() {
+                panic!("hvec! element count exceeds capacity");
+            }
+        )+
+        v
+    }};
+}
+
+/// Prints formatted text to the standard output.
+/// 
+/// This macro provides formatted printing functionality in no_std environments.
+/// It validates format strings at compile time.
+/// Output is emitted under a global stdout lock, so prints from different
+/// threads never interleave.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {{
+        // Create a static buffer for output
+        static PRINT_BUFFER: $crate::StaticCell<$crate::Buffer> = $crate::StaticCell::new();
+
+        $crate::_print_buffered(&PRINT_BUFFER, core::format_args!($($arg)*));
+    }};
+}
+
+/// Prints formatted text and returns the number of bytes written.
+///
+/// This behaves like `print!` but reports the length of the formatted
+/// output, which is useful for tracking output volume (rate limiting,
+/// pagination) without a separate `format!` + `len()` pass.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::print_len;
+///
+/// let written = print_len!("{}-{}\n", "abc", 42);
+/// assert_eq!(written, 7);
+/// ```
+#[macro_export]
+macro_rules! print_len {
+    ($($arg:tt)*) => {{
+        static PRINT_BUFFER: $crate::StaticCell<$crate::Buffer> = $crate::StaticCell::new();
+        $crate::_print_buffered(&PRINT_BUFFER, core::format_args!($($arg)*))
+    }};
+}
+
+/// Global lock serializing output so concurrent prints never interleave.
+static STDOUT_LOCK: SpinLock<()> = SpinLock::new(());
+
+/// Bytes written by `_print`, captured so tests can inspect the output.
+#[cfg(test)]
+static PRINT_CAPTURE: SpinLock<Vec<u8>> = SpinLock::new(Vec::new());
+
+/// Formats `args` into a print macro's `buffer` and prints the result,
+/// returning the number of bytes printed.
+///
+/// Formatting runs user code (e.g. a `Debug` impl) that may print itself,
+/// so it happens before the stdout lock is taken and only emitting the
+/// result is serialized. A print that finds the call site's buffer claimed,
+/// by a print on another thread or by the print it is nested in, formats
+/// into a fresh buffer instead.
+#[doc(hidden)]
+pub fn _print_buffered(buffer: &StaticCell<Buffer>, args: core::fmt::Arguments) -> usize {
+    if buffer.get().is_none() {
+        buffer.try_init(Buffer::with_capacity(DEFAULT_BUFFER_SIZE));
+    }
+    let shared = buffer.wait();
+    let claimed = !shared.claimed.swap(true, Ordering::Acquire);
+
+    let fresh;
+    let buffer = if claimed {
+        shared
+    } else {
+        fresh = Buffer::with_capacity(DEFAULT_BUFFER_SIZE);
+        &fresh
+    };
+
+    // SAFETY: the claim, or the buffer being local, gives this call
+    // exclusive use of the buffer
+    let len = unsafe {
+        *buffer.pos.get() = 0;
+        let _ = write(buffer, args);
+        let len = *buffer.pos.get();
+        let _stdout = STDOUT_LOCK.lock();
+        _print(core::str::from_utf8_unchecked(&(&*buffer.buf.get())[..len]));
+        len
+    };
+    if claimed {
+        shared.claimed.store(false, Ordering::Release);
+    }
+    len
+}
+
+/// Internal function to handle actual printing.
+#[doc(hidden)]
+pub fn _print(s: &str) {
+    // Implementation depends on target platform
+    #[cfg(target_arch = "wasm32")]
+    {
+        // Web assembly implementation
+        extern "C" {
+            fn console_log(ptr: *const u8, len: usize);
+        }
+        unsafe {
+            console_log(s.as_ptr(), s.len());
+        }
+    }
+    
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // Default implementation using core::fmt::Write
+        use core::fmt::Write;
+        struct Stdout;
+        
+        impl Write for Stdout {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                extern "C" {
+                    fn putchar(c: i32) -> i32;
+                }
+                for byte in s.bytes() {
+                    #[cfg(test)]
+                    PRINT_CAPTURE.lock().push(byte);
+                    unsafe {
+                        putchar(byte as i32);
+                    }
+                }
+                Ok(())
+            }
+        }
+        
+        let mut stdout = Stdout;
+        let _ = stdout.write_str(s);
+    }
+}
+
+/// Prints formatted text to the standard output, with a newline.
+/// 
+/// # Understanding println!
+/// This macro extends the `print!` macro by automatically adding a newline
+/// at the end of the output. It's essential for formatted console output
+/// in no_std environments.
+/// 
+/// # How It Works
+/// 1. Formats the text using the same rules as `print!`
+/// 2. Appends a newline character (`\n`)
+/// 3. Writes to the output in a single operation
+/// 
+/// # Examples
+/// ```rust
+/// use noir_macros_core::println;
+/// use noir_macros_core::vec;
+///
+/// // Basic usage
+/// println!("Hello, World!");
+/// 
+/// // With formatting
+/// let name = "Rust";
+/// println!("Learning {}", name);
+/// 
+/// // Multiple values
+/// let (x, y) = (10, 20);
+/// println!("Point: ({}, {})", x, y);
+/// 
+/// // Debug formatting
+/// let data = vec![1, 2, 3];
+/// println!("Data: {:?}", data);
+/// ```
+/// 
+/// # Common Use Cases
+/// 1. Debug output
+/// 2. User interaction
+/// 3. Logging information
+/// 4. Progress reporting
+/// 
+/// # Best Practices
+/// 1. Use for human-readable output
+/// 2. Consider buffering for many prints
+/// 3. Use debug format `{:?}` for complex types
+/// 4. Avoid in performance-critical loops
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::print!("{}\n", format_args!($($arg)*))
+    };
+}
+
+/// Prints rows of text cells as a table with aligned columns.
+///
+/// Every column is padded to its widest cell and columns are separated by
+/// two spaces. The rows can be any array or slice of `&str` arrays or
+/// slices; shorter rows simply end early. The whole table is printed as a
+/// single output, so it is never interleaved with other prints.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::print_table;
+///
+/// print_table!([
+///     ["Name", "Value"],
+///     ["clk", "48MHz"],
+///     ["vcore", "1.2V"],
+/// ]);
+/// // Name   Value
+/// // clk    48MHz
+/// // vcore  1.2V
+/// ```
+#[macro_export]
+macro_rules! print_table {
+    ($rows:expr) => {
+        $crate::_print_table(&$rows)
+    };
+}
+
+/// Prints `rows` for [`print_table!`].
+#[doc(hidden)]
+pub fn _print_table<'a, R: AsRef<[&'a str]>>(rows: &[R]) {
+    struct Table<'t, R>(&'t [R]);
+
+    impl<'a, R: AsRef<[&'a str]>> core::fmt::Display for Table<'_, R> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            let width = |column: usize| {
+                self.0
+                    .iter()
+                    .filter_map(|row| row.as_ref().get(column))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            };
+            for row in self.0 {
+                let cells = row.as_ref();
+                for (column, cell) in cells.iter().enumerate() {
+                    if column + 1 == cells.len() {
+                        f.write_str(cell)?;
+                    } else {
+                        write!(f, "{:<width$}  ", cell, width = width(column))?;
+                    }
+                }
+                f.write_str("\n")?;
+            }
+            Ok(())
+        }
+    }
+
+    static TABLE_BUFFER: StaticCell<Buffer> = StaticCell::new();
+    _print_buffered(&TABLE_BUFFER, format_args!("{}", Table(rows)));
+}
+
+/// Internal helper struct for print macro.
+/// 
+/// This type implements `fmt::Write` to enable formatted printing
+/// in no_std environments. It's used internally by the print
+/// macro implementation.
+/// 
+/// # Implementation Notes
+/// - Provides a no-op implementation of `write_str`
+/// - Used for compile-time format string validation
+#[doc(hidden)]
+pub struct PrintWrapper;
+
+impl core::fmt::Write for PrintWrapper {
+    /// Implements the write_str method required by fmt::Write.
+    /// This is a no-op implementation used only for compile-time
+    /// format string validation.
+    fn write_str(&mut self, _s: &str) -> core::fmt::Result {
+        Ok(())
+    }
+}
+
+/// The default size for new buffers.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// The maximum allowed buffer size.
+pub const MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
+// Capacity of the most recently created buffer on this thread, so tests
+// can observe how large a buffer the macros request.
+#[cfg(test)]
+std::thread_local!(static LAST_BUFFER_CAPACITY: core::cell::Cell<usize> = const { core::cell::Cell::new(0) });
+
+/// A buffer for storing formatted strings with configurable size.
+#[doc(hidden)]
+pub struct Buffer {
+    pub buf: UnsafeCell<Vec<u8>>,
+    pub pos: UnsafeCell<usize>,
+    pub capacity: usize,
+    pub limit: usize,
+    /// Set while a print macro formats into this buffer.
+    claimed: AtomicBool,
+}
+
+impl Buffer {
+    /// Creates a new buffer with the default capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates a new buffer with the specified capacity.
+    /// 
+    /// # Safety
+    /// The capacity must be less than or equal to MAX_BUFFER_SIZE.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_limit(capacity, MAX_BUFFER_SIZE)
+    }
+
+    /// Creates a new buffer that never grows beyond `limit` bytes.
+    ///
+    /// # Panics
+    /// Panics if `limit` exceeds MAX_BUFFER_SIZE or `capacity` exceeds `limit`.
+    pub fn with_limit(capacity: usize, limit: usize) -> Self {
+        assert!(limit <= MAX_BUFFER_SIZE, "Buffer capacity exceeds maximum allowed size");
+        assert!(capacity <= limit, "Buffer capacity exceeds its limit");
+        #[cfg(test)]
+        LAST_BUFFER_CAPACITY.with(|last| last.set(capacity));
+        Self {
+            buf: UnsafeCell::new(Vec::with_capacity(capacity)),
+            pos: UnsafeCell::new(0),
+            capacity,
+            limit,
+            claimed: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns true if the buffer has enough space for additional bytes.
+    #[inline]
+    pub fn has_capacity(&self, additional: usize) -> bool {
+        unsafe { *self.pos.get() + additional <= self.capacity }
+    }
+
+    /// Attempts to grow the buffer to accommodate more data.
+    /// Returns true if successful, false if the new size would exceed the buffer's limit.
+    pub fn try_grow(&self, required: usize) -> bool {
+        unsafe {
+            let current_pos = *self.pos.get();
+            let needed = current_pos + required;
+            let new_size = needed.next_power_of_two().min(self.limit);
+            
+            if needed <= self.limit {
+                let buf = &mut *self.buf.get();
+                if buf.len() < new_size {
+                    buf.reserve(new_size - buf.len());
+                    buf.resize(new_size, 0);
+                }
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Appends `s`, growing the buffer if needed.
+    /// Returns an error if the buffer would exceed its limit.
+    pub fn push_str(&mut self, s: &str) -> core::fmt::Result {
+        // SAFETY: `&mut self` rules out any other access to the buffer
+        unsafe { self.append(s) }
+    }
+
+    /// [`push_str`](Self::push_str) through a shared reference, for the
+    /// static buffers of the formatting macros.
+    ///
+    /// # Safety
+    /// No other thread may access the buffer during the call, and no `&str`
+    /// previously read from the buffer may be used afterwards.
+    unsafe fn append(&self, s: &str) -> core::fmt::Result {
+        let bytes = s.as_bytes();
+        let pos = unsafe { *self.pos.get() };
+
+        if !self.has_capacity(bytes.len()) && !self.try_grow(bytes.len()) {
+            return Err(core::fmt::Error);
+        }
+
+        unsafe {
+            let buf = &mut *self.buf.get();
+            if buf.len() < pos + bytes.len() {
+                let new_len = (pos + bytes.len()).next_power_of_two().min(self.limit);
+                buf.resize(new_len, 0);
+            }
+            buf[pos..pos + bytes.len()].copy_from_slice(bytes);
+            *self.pos.get() = pos + bytes.len();
+        }
+        Ok(())
+    }
+
+    /// Appends the decimal form of `n` without going through `core::fmt`.
+    pub fn write_u64(&mut self, n: u64) -> core::fmt::Result {
+        let mut digits = [0u8; 20];
+        self.push_str(format_u64(n, &mut digits))
+    }
+
+    /// Appends the decimal form of `n` without going through `core::fmt`.
+    pub fn write_i64(&mut self, n: i64) -> core::fmt::Result {
+        if n < 0 {
+            self.push_str("-")?;
+        }
+        self.write_u64(n.unsigned_abs())
+    }
+
+    /// Appends `n` with `precision` fractional digits (at most 9), rounded
+    /// half away from zero.
+    ///
+    /// Values whose integer part does not fit a `u64` fall back to
+    /// `core::fmt`. NaN and infinities are written as `NaN`, `inf` and `-inf`.
+    pub fn write_f64(&mut self, n: f64, precision: usize) -> core::fmt::Result {
+        const SCALES: [u64; 10] = [1, 10, 100, 1_000, 10_000, 100_000, 1_000_000, 10_000_000, 100_000_000, 1_000_000_000];
+
+        if n.is_nan() {
+            return self.push_str("NaN");
+        }
+        let precision = precision.min(9);
+        let negative = n.is_sign_negative() && n != 0.0;
+        let magnitude = if negative { -n } else { n };
+        if magnitude >= u64::MAX as f64 {
+            return core::fmt::Write::write_fmt(self, format_args!("{:.*}", precision, n));
+        }
+
+        let scale = SCALES[precision];
+        let mut integer = magnitude as u64;
+        let mut fraction = ((magnitude - integer as f64) * scale as f64 + 0.5) as u64;
+        if fraction >= scale {
+            integer += 1;
+            fraction -= scale;
+        }
+
+        if negative {
+            self.push_str("-")?;
+        }
+        self.write_u64(integer)?;
+        if precision > 0 {
+            let mut digits = [b'0'; 20];
+            let fraction = format_u64(fraction, &mut digits);
+            self.push_str(".")?;
+            for _ in fraction.len()..precision {
+                self.push_str("0")?;
+            }
+            self.push_str(fraction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the decimal digits of `n` to the end of `digits` and returns them.
+fn format_u64(mut n: u64, digits: &mut [u8; 20]) -> &str {
+    const PAIRS: &[u8; 200] = b"\
+        0001020304050607080910111213141516171819\
+        2021222324252627282930313233343536373839\
+        4041424344454647484950515253545556575859\
+        6061626364656667686970717273747576777879\
+        8081828384858687888990919293949596979899";
+
+    let mut start = digits.len();
+    while n >= 100 {
+        let pair = (n % 100) as usize * 2;
+        n /= 100;
+        start -= 2;
+        digits[start..start + 2].copy_from_slice(&PAIRS[pair..pair + 2]);
+    }
+    if n >= 10 {
+        let pair = n as usize * 2;
+        start -= 2;
+        digits[start..start + 2].copy_from_slice(&PAIRS[pair..pair + 2]);
+    } else {
+        start -= 1;
+        digits[start] = b'0' + n as u8;
+    }
+    // SAFETY: only ASCII digits were written
+    unsafe { core::str::from_utf8_unchecked(&digits[start..]) }
+}
+
+// SAFETY: Access to Buffer is synchronized through StaticCell and we ensure
+// single-threaded access during writes through atomic operations.
+// The Buffer is effectively immutable between writes due to the StaticCell
+// synchronization, and all modifications are done through UnsafeCell which
+// provides interior mutability in a controlled manner.
+unsafe impl Sync for Buffer {}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Write for Buffer {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push_str(s)
+    }
+}
+
+/// A helper function to write formatted arguments to a buffer through a shared reference.
+#[doc(hidden)]
+pub fn write(buffer: &Buffer, args: core::fmt::Arguments) -> core::fmt::Result {
+    struct WriteAdapter<'a>(&'a Buffer);
+
+    impl<'a> core::fmt::Write for WriteAdapter<'a> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            // SAFETY: callers serialize access to shared buffers, as the
+            // formatting macros do through their locks
+            unsafe { self.0.append(s) }
+        }
+    }
+    core::fmt::write(&mut WriteAdapter(buffer), args)
+}
+
+/// A macro for formatting text in a no_std environment.
+/// 
+/// This macro provides string formatting capabilities similar to the standard library's
+/// `format!` macro, but designed specifically for no_std environments. It uses a dynamic
+/// buffer for formatting and is thread-safe.
+/// 
+/// # Features
+/// - Thread-safe formatting using static buffers
+/// - Compile-time format string validation
+/// - Dynamic buffer growth up to 1MB
+/// - Efficient memory usage with small initial buffer
+/// - Error handling for buffer overflow
+/// 
+/// # Examples
+/// 
+/// Basic string formatting:
+/// ```rust
+/// use noir_macros_core::format;
+/// 
+/// let name = "World";
+/// let greeting = format!("Hello, {}!", name);
+/// assert_eq!(greeting, "Hello, World!");
+/// ```
+/// 
+/// Multiple arguments and different types:
+/// ```rust
+/// use noir_macros_core::format;
+/// 
+/// let count = 42;
+/// let value = 3.14;
+/// let result = format!("Count: {}, Value: {:.2}", count, value);
+/// assert_eq!(result, "Count: 42, Value: 3.14");
+/// ```
+/// 
+/// # Buffer Size
+/// - Initial buffer size: twice the format string length, between 64 bytes
+///   and 8KB (DEFAULT_BUFFER_SIZE)
+/// - Maximum buffer size: 1MB (MAX_BUFFER_SIZE)
+/// - Buffer grows dynamically as needed
+/// - Returns error if formatted string would exceed maximum size
+#[macro_export]
+macro_rules! format {
+    ($fmt:literal $($rest:tt)*) => {
+        $crate::format!(@capacity $crate::format_capacity_hint($fmt.len()); $fmt $($rest)*)
+    };
+    (@capacity $capacity:expr; $($arg:tt)*) => {{
+        // Validate format string at compile time
+        let _ = {
+            #[allow(unused_imports)]
+            use core::fmt::Write;
+            let mut _pw = $crate::PrintWrapper {};
+            core::fmt::write(&mut _pw, core::format_args!($($arg)*))
+        };
+        
+        static BUFFER: $crate::StaticCell<$crate::Buffer> = $crate::StaticCell::new();
+        
+        // Initialize buffer if not already initialized
+        if BUFFER.get().is_none() {
+            BUFFER.try_init($crate::Buffer::with_capacity($capacity));
+        }
+        
+        // Get reference to buffer and format string
+        if let Some(buffer) = BUFFER.get() {
+            unsafe {
+                *buffer.pos.get() = 0;
                 let _ = $crate::write(buffer, core::format_args!($($arg)*));
-                let output = core::str::from_utf8_unchecked(&(*buffer.buf.get())[..*buffer.pos.get()]);
-                $crate::_print(output);
+                core::str::from_utf8_unchecked(&(&*buffer.buf.get())[..*buffer.pos.get()])
+            }
+        } else {
+            "" // Return empty string if buffer not available
+        }
+    }};
+    ($($arg:tt)*) => {
+        $crate::format!(@capacity $crate::DEFAULT_BUFFER_SIZE; $($arg)*)
+    };
+}
+
+/// Returns the initial buffer capacity `format!` uses for a format string
+/// of `format_len` bytes.
+///
+/// The output is guessed to be about twice as long as the format string,
+/// with at least 64 bytes and at most [`DEFAULT_BUFFER_SIZE`]; the buffer
+/// still grows on demand if the guess is too small.
+#[doc(hidden)]
+pub const fn format_capacity_hint(format_len: usize) -> usize {
+    let hint = format_len.saturating_mul(2);
+    if hint < 64 {
+        64
+    } else if hint > DEFAULT_BUFFER_SIZE {
+        DEFAULT_BUFFER_SIZE
+    } else {
+        hint
+    }
+}
+
+/// Formats text into a caller-provided [`Buffer`].
+///
+/// This is the buffer-parameterized version of `format!`: the buffer's
+/// position is reset, the arguments are written into it, and the returned
+/// `&str` borrows from that buffer. Owning the buffer avoids sharing the
+/// static buffer of `format!` across calls. The buffer is taken by `&mut`,
+/// so the text cannot outlive it or be overwritten while still borrowed.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{format_with, Buffer};
+///
+/// let mut buffer = Buffer::with_capacity(64);
+/// let message = format_with!(&mut buffer, "{} + {} = {}", 1, 2, 1 + 2);
+/// assert_eq!(message, "1 + 2 = 3");
+/// ```
+///
+/// ```rust,compile_fail
+/// use noir_macros_core::{format_with, Buffer};
+///
+/// let mut buffer = Buffer::with_capacity(64);
+/// let first = format_with!(&mut buffer, "first");
+/// let second = format_with!(&mut buffer, "second");
+/// assert_eq!(first, "first");
+/// ```
+#[macro_export]
+macro_rules! format_with {
+    ($buf:expr, $($arg:tt)*) => {
+        $crate::format_into($buf, core::format_args!($($arg)*))
+    };
+}
+
+/// Formats `args` into `buffer` from its start and returns the text, for
+/// [`format_with!`].
+///
+/// Output that does not fit within the buffer's limit is dropped.
+#[doc(hidden)]
+pub fn format_into<'a>(buffer: &'a mut Buffer, args: core::fmt::Arguments) -> &'a str {
+    *buffer.pos.get_mut() = 0;
+    let _ = write(buffer, args);
+    let len = *buffer.pos.get_mut();
+    // SAFETY: `write` only appends whole `&str`s
+    unsafe { core::str::from_utf8_unchecked(&buffer.buf.get_mut()[..len]) }
+}
+
+/// Formats text into any [`core::fmt::Write`] implementor.
+///
+/// Works like `write!`, but does not require `core::fmt::Write` to be in
+/// scope and accepts the writer by value or reference. Returns the
+/// `core::fmt::Result` so failures can be propagated with `?`. Useful for
+/// UART writers, `heapless::String` and other fixed-capacity sinks.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::fmt_to;
+///
+/// struct Uart {
+///     sent: usize,
+/// }
+///
+/// impl core::fmt::Write for Uart {
+///     fn write_str(&mut self, s: &str) -> core::fmt::Result {
+///         self.sent += s.len(); // e.g. push the bytes to the TX FIFO
+///         Ok(())
+///     }
+/// }
+///
+/// let mut uart = Uart { sent: 0 };
+/// fmt_to!(uart, "temp={}C", 21).unwrap();
+/// assert_eq!(uart.sent, 8);
+/// ```
+#[macro_export]
+macro_rules! fmt_to {
+    ($writer:expr, $($arg:tt)*) => {{
+        use core::fmt::Write as _;
+        $writer.write_fmt(core::format_args!($($arg)*))
+    }};
+}
+
+/// Writes a line to any [`core::fmt::Write`] implementor, such as a HAL
+/// serial port.
+///
+/// Like [`fmt_to!`] followed by a `\n`, which is written only if the
+/// formatted text was. Returns the `core::fmt::Result`. Without format
+/// arguments, only the newline is written.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::uart_println;
+///
+/// struct Uart {
+///     tx: Vec<u8>,
+/// }
+///
+/// impl core::fmt::Write for Uart {
+///     fn write_str(&mut self, s: &str) -> core::fmt::Result {
+///         self.tx.extend_from_slice(s.as_bytes()); // e.g. fill the TX FIFO
+///         Ok(())
+///     }
+/// }
+///
+/// let mut uart = Uart { tx: Vec::new() };
+/// uart_println!(uart, "boot ok, {} MHz", 48).unwrap();
+/// assert_eq!(uart.tx, b"boot ok, 48 MHz\n");
+/// ```
+#[macro_export]
+macro_rules! uart_println {
+    ($writer:expr $(,)?) => {{
+        use core::fmt::Write as _;
+        $writer.write_str("\n")
+    }};
+    ($writer:expr, $($arg:tt)*) => {{
+        use core::fmt::Write as _;
+        $writer.write_fmt(core::format_args!("{}\n", core::format_args!($($arg)*)))
+    }};
+}
+
+/// Formatting deferred until the value is displayed.
+///
+/// Created by [`lazy_format!`].
+pub struct LazyFormat<F> {
+    format: F,
+}
+
+impl<F: Fn(&mut core::fmt::Formatter<'_>) -> core::fmt::Result> LazyFormat<F> {
+    /// Wraps `format`, which runs each time the value is displayed.
+    #[inline]
+    pub const fn new(format: F) -> Self {
+        Self { format }
+    }
+}
+
+impl<F: Fn(&mut core::fmt::Formatter<'_>) -> core::fmt::Result> core::fmt::Display for LazyFormat<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        (self.format)(f)
+    }
+}
+
+/// Returns a value implementing `Display` that formats its arguments only
+/// when it is written.
+///
+/// The arguments, including any function calls in them, are evaluated
+/// each time the value is displayed and not at all if it never is, which
+/// saves the formatting cost of messages that end up filtered out. Like a
+/// `move` closure, the value takes ownership of the variables it uses, so
+/// it can be returned or passed on.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::lazy_format;
+///
+/// fn expensive_dump() -> u32 {
+///     0x1234
+/// }
+///
+/// let message = lazy_format!("registers: {:#x}", expensive_dump());
+/// // nothing has been formatted or called yet
+/// assert_eq!(message.to_string(), "registers: 0x1234");
+/// ```
+#[macro_export]
+macro_rules! lazy_format {
+    ($($arg:tt)*) => {
+        $crate::LazyFormat::new(move |f: &mut core::fmt::Formatter<'_>| core::write!(f, $($arg)*))
+    };
+}
+
+/// Formats text like `format!`, but never grows its buffer beyond `MAX` bytes.
+///
+/// `format!` lets its buffer grow up to 1MB, which can exhaust the RAM of a
+/// small target. `format_bounded!` formats into an [`ArrayBuffer`] of the
+/// constant `MAX` bytes and returns it by value, or `Err(core::fmt::Error)`
+/// when the output would not fit, instead of allocating more.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::format_bounded;
+///
+/// assert_eq!(format_bounded!(16, "id={}", 7).unwrap().as_str(), "id=7");
+/// assert!(format_bounded!(4, "{}", "too long").is_err());
+/// ```
+#[macro_export]
+macro_rules! format_bounded {
+    ($max:expr, $($arg:tt)*) => {{
+        let mut buffer = $crate::ArrayBuffer::<{ $max }>::new();
+        match core::fmt::Write::write_fmt(&mut buffer, core::format_args!($($arg)*)) {
+            Ok(()) => Ok(buffer),
+            Err(error) => Err(error),
+        }
+    }};
+}
+
+/// A macro for debug formatting in no_std environments.
+///
+/// This macro works similarly to the standard library's `dbg!` macro but is
+/// designed for no_std environments. It prints the expression and its value,
+/// and returns the value.
+///
+/// # Examples
+///
+/// ```rust
+/// use noir_macros_core::debug;
+///
+/// let x = 42;
+/// let y = debug!(x + 1); // prints "[DEBUG] x + 1 = 43"
+/// assert_eq!(y, 43);
+/// ```
+#[macro_export]
+macro_rules! debug {
+    ($val:expr) => {{
+        match $val {
+            tmp => {
+                $crate::println!("[DEBUG] {} = {:?}", stringify!($val), &tmp);
+                tmp
+            }
+        }
+    }};
+    ($($val:expr),+ $(,)?) => {
+        ($($crate::debug!($val)),+,)
+    };
+}
+
+enum_names! {
+    /// The severity of a [`log!`] message, from most to least severe.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Level {
+        /// Failures that need attention.
+        Error = 1,
+        /// Unexpected conditions the program recovered from.
+        Warn,
+        /// High-level progress.
+        Info,
+        /// Details for debugging.
+        Debug,
+        /// Very verbose tracing.
+        Trace,
+    }
+}
+
+/// The maximum number of modules with their own level.
+pub const MAX_MODULE_LEVELS: usize = 8;
+
+/// The level of modules without a level of their own, as a `Level` value.
+static LOG_LEVEL: AtomicUsize = AtomicUsize::new(Level::Info as usize);
+
+/// A module filter set with [`set_module_level`].
+struct ModuleLevel {
+    filter: StaticCell<&'static str>,
+    level: AtomicUsize,
+}
+
+/// The levels set with [`set_module_level`]. Slots are claimed once and
+/// read without locking, so logging from an interrupt never blocks.
+static MODULE_LEVELS: [ModuleLevel; MAX_MODULE_LEVELS] = [const {
+    ModuleLevel {
+        filter: StaticCell::new(),
+        level: AtomicUsize::new(0),
+    }
+}; MAX_MODULE_LEVELS];
+
+/// Serializes [`set_module_level`], so a module never claims two slots.
+static MODULE_LEVELS_WRITE: SpinLock<()> = SpinLock::new(());
+
+/// Sets the most verbose level logged by modules without a level of their
+/// own. Defaults to [`Level::Info`].
+pub fn set_log_level(level: Level) {
+    LOG_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Sets the most verbose level logged by `module` and its submodules,
+/// overriding the global level.
+///
+/// `module` is a path such as `"driver::i2c"`, with or without the leading
+/// crate name. When several filters match, the longest one applies.
+/// Returns `false` if [`MAX_MODULE_LEVELS`] other modules already have a
+/// level.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{log_enabled, set_module_level, Level};
+///
+/// assert!(set_module_level("driver::i2c", Level::Debug));
+///
+/// assert!(log_enabled(Level::Debug, "firmware::driver::i2c"));
+/// assert!(!log_enabled(Level::Debug, "firmware::driver::spi"));
+/// ```
+pub fn set_module_level(module: &'static str, level: Level) -> bool {
+    let _write = MODULE_LEVELS_WRITE.lock();
+    if let Some(slot) = MODULE_LEVELS.iter().find(|slot| slot.filter.get() == Some(&module)) {
+        slot.level.store(level as usize, Ordering::Relaxed);
+        return true;
+    }
+    match MODULE_LEVELS.iter().find(|slot| !slot.filter.is_initialized()) {
+        Some(free) => {
+            // the level is in place before `try_init` publishes the filter
+            free.level.store(level as usize, Ordering::Relaxed);
+            free.filter.try_init(module)
+        }
+        None => false,
+    }
+}
+
+/// Returns true if a message at `level` from `module_path` passes the
+/// module and global levels.
+pub fn log_enabled(level: Level, module_path: &str) -> bool {
+    // `module_path!()` starts with the crate name, which filters may omit
+    let without_crate = module_path.split_once("::").map(|(_, rest)| rest);
+    let covers = |filter: &str, path: &str| {
+        path.strip_prefix(filter)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    };
+
+    let module_level = MODULE_LEVELS
+        .iter()
+        .filter_map(|slot| slot.filter.get().map(|filter| (*filter, slot)))
+        .filter(|(filter, _)| {
+            covers(filter, module_path) || without_crate.is_some_and(|path| covers(filter, path))
+        })
+        .max_by_key(|(filter, _)| filter.len())
+        .map(|(_, slot)| slot.level.load(Ordering::Relaxed));
+    level as usize <= module_level.unwrap_or_else(|| LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Prints a message tagged with its [`Level`], if the level is enabled for
+/// the calling module.
+///
+/// The calling module is taken from `module_path!()` and checked with
+/// [`log_enabled`] before anything is formatted, so filtered messages cost
+/// only the level lookup.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{log, set_log_level, Level};
+///
+/// set_log_level(Level::Warn);
+/// log!(Level::Warn, "vbat low: {} mV", 3100); // prints "[Warn] vbat low: 3100 mV"
+/// log!(Level::Info, "not printed");
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {{
+        let level: $crate::Level = $level;
+        if $crate::log_enabled(level, module_path!()) {
+            $crate::println!("[{}] {}", level.as_str(), format_args!($($arg)+));
+        }
+    }};
+}
+
+/// A macro for defining bit flags in a type-safe way.
+///
+/// This macro creates a type-safe bit flag enum that can be combined
+/// using bitwise operations.
+///
+/// # What are Bitflags?
+/// Bitflags are a programming pattern where individual bits in an integer are used
+/// to represent boolean flags. This is memory-efficient and allows for fast operations.
+///
+/// # Why Use Bitflags?
+/// - Memory Efficient: Multiple flags in a single integer
+/// - Fast Operations: Bitwise operations are very fast
+/// - Type Safe: Rust's type system prevents invalid combinations
+///
+/// # How Bitflags Work
+/// Each flag is a power of 2 (1, 2, 4, 8, 16, etc.) so that each bit represents
+/// a unique flag:
+/// ```text
+/// Bit Position:  7  6  5  4  3  2  1  0
+/// Binary:        0  0  0  0  0  1  0  1
+///                            ↑  ↑  ↑  ↑
+///                            8  4  2  1
+/// ```
+///
+/// # Example Usage
+/// ```rust
+/// use noir_macros_core::bitflags;
+/// bitflags! {
+///     /// File permissions in a Unix-like system
+///     pub struct Permissions: u8 {
+///         const READ    = 0b0000_0100;  // 4 in decimal
+///         const WRITE   = 0b0000_0010;  // 2 in decimal
+///         const EXECUTE = 0b0000_0001;  // 1 in decimal
+///     }
+/// }
+///
+/// // Combine permissions using bitwise OR (|)
+/// let read_write = Permissions::READ | Permissions::WRITE;
+///
+/// // Check permissions using contains()
+/// assert!(read_write.contains(Permissions::READ));
+/// assert!(!read_write.contains(Permissions::EXECUTE));
+/// ```
+///
+/// # Unknown Bits
+/// By default the flags are strict: `from_bits` rejects any bit that is not
+/// covered by a declared flag. Appending `with retain_unknown` after the
+/// body makes the type forward compatible instead: `from_bits` accepts and
+/// preserves undeclared bits, so values from a newer peer of a wire protocol
+/// round-trip unchanged. In both modes `known()` and `known_bits()` strip
+/// the undeclared bits while `bits()` returns them all.
+///
+/// ```rust
+/// use noir_macros_core::bitflags;
+/// bitflags! {
+///     pub struct Caps: u8 {
+///         const CRC = 0b01;
+///         const ACK = 0b10;
+///     } with retain_unknown
+/// }
+///
+/// // 0b100 was added by a newer protocol version
+/// let caps = Caps::from_bits(0b111).unwrap();
+/// assert_eq!(caps.bits(), 0b111);
+/// assert_eq!(caps.known(), Caps::CRC | Caps::ACK);
+/// ```
+///
+/// # Default Flags
+/// `with default A | B` after the body sets the flags returned by
+/// `Default::default()` and by the `const fn default_flags()`, which also
+/// works in `static` and `const` initializers. Without it the type does not
+/// implement `Default`. Options can be combined, e.g.
+/// `with default A with retain_unknown`.
+///
+/// ```rust
+/// use noir_macros_core::bitflags;
+/// bitflags! {
+///     pub struct Perms: u8 {
+///         const READ = 0b001;
+///         const WRITE = 0b010;
+///         const EXECUTE = 0b100;
+///     } with default READ | WRITE
+/// }
+///
+/// struct Config {
+///     perms: Perms,
+/// }
+///
+/// static CONFIG: Config = Config {
+///     perms: Perms::default_flags(),
+/// };
+///
+/// assert_eq!(Perms::default(), Perms::READ | Perms::WRITE);
+/// assert_eq!(CONFIG.perms, Perms::default());
+/// ```
+///
+/// # Flag Values
+/// Each value is evaluated as the backing integer type, so a value that
+/// does not fit, such as `1 << 8` or `0x100` on a `u8`, fails to compile
+/// through rustc's overflow checks:
+///
+/// ```rust,compile_fail
+/// use noir_macros_core::bitflags;
+/// bitflags! {
+///     pub struct Lines: u8 {
+///         const LINE_8 = 1 << 8;
+///     }
+/// }
+/// ```
+///
+/// ```rust,compile_fail
+/// use noir_macros_core::bitflags;
+/// bitflags! {
+///     pub struct Lines: u8 {
+///         const LINE_8 = 0x100;
+///     }
+/// }
+/// ```
+///
+/// # Groups
+/// Named masks over several flags are declared after the flags with
+/// `group NAME = A | B;`. A group is a constant like the flags themselves,
+/// but it is not a declared flag: it is skipped by `iter()`, `Display` and
+/// the other per-flag operations.
+///
+/// ```rust
+/// use noir_macros_core::bitflags;
+/// bitflags! {
+///     pub struct Mode: u16 {
+///         const READ = 1 << 0;
+///         const WRITE = 1 << 1;
+///         const EXECUTE = 1 << 2;
+///         const SETUID = 1 << 11;
+///         group ALL_PERMS = READ | WRITE | EXECUTE;
+///     }
+/// }
+///
+/// assert!(Mode::ALL_PERMS.contains(Mode::WRITE));
+/// assert!(!Mode::ALL_PERMS.contains(Mode::SETUID));
+/// ```
+///
+/// # Common Operations
+/// - `|` (OR): Combine flags
+/// - `&` (AND): Check if flags are present
+/// - `^` (XOR): Toggle flags
+/// - `!` (NOT): Invert flags
+///
+/// # Best Practices
+/// 1. Use descriptive names for your flags
+/// 2. Document the purpose of each flag
+/// 3. Group related flags together
+/// 4. Consider using a larger integer type (u32, u64) if you need many flags
+#[macro_export]
+macro_rules! bitflags {
+    (
+        @options [$($head:tt)*] { $($body:tt)* } [$retain:tt] [$($default:ident)*]
+        with retain_unknown $($rest:tt)*
+    ) => {
+        $crate::bitflags!(@options [$($head)*] { $($body)* } [true] [$($default)*] $($rest)*);
+    };
+    (
+        @options [$($head:tt)*] { $($body:tt)* } [$retain:tt] [$($default:ident)*]
+        with default $flag:ident $($rest:tt)*
+    ) => {
+        $crate::bitflags!(@default_flags [$($head)*] { $($body)* } [$retain] [$flag] $($rest)*);
+    };
+    (
+        @default_flags [$($head:tt)*] { $($body:tt)* } [$retain:tt] [$($default:ident)+]
+        | $flag:ident $($rest:tt)*
+    ) => {
+        $crate::bitflags!(@default_flags [$($head)*] { $($body)* } [$retain] [$($default)+ $flag] $($rest)*);
+    };
+    (@default_flags [$($head:tt)*] { $($body:tt)* } [$retain:tt] [$($default:ident)+] $($rest:tt)*) => {
+        $crate::bitflags!(@options [$($head)*] { $($body)* } [$retain] [$($default)+] $($rest)*);
+    };
+    (@options [$($head:tt)*] { $($body:tt)* } [$retain:tt] [$($default:ident)*]) => {
+        $crate::bitflags!(@emit [$retain] [$($default)*] $($head)* { $($body)* });
+    };
+    (@default [$vis:vis] $name:ident []) => {};
+    (@default [$vis:vis] $name:ident [$($default:ident)+]) => {
+        impl $name {
+            /// Returns the flags declared with `with default`.
+            #[inline]
+            $vis const fn default_flags() -> Self {
+                Self(0 $(| Self::$default.0)+)
+            }
+        }
+
+        impl Default for $name {
+            #[inline]
+            fn default() -> Self {
+                Self::default_flags()
+            }
+        }
+    };
+    (
+        @emit [$retain:tt] [$($default:ident)*]
+        $(#[$outer:meta])*
+        $vis:vis struct $name:ident: $type:ty {
+            $(
+                $(#[$inner:meta])*
+                const $flag:ident = $value:expr;
+            )*
+            $(
+                group $group:ident = $($member:ident)|+;
+            )*
+        }
+    ) => {
+        $(#[$outer])*
+        #[derive(Copy, Clone, PartialEq, Eq)]
+        #[repr(transparent)]
+        $vis struct $name($type);
+
+        impl core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("bits", &format!("{:#b}", self.0))
+                    .finish()
+            }
+        }
+
+        /// Formats the flags as declared names joined by `|`, e.g. `READ|WRITE`.
+        ///
+        /// Bits not covered by any declared flag are appended as a hex token
+        /// and no flags format as the empty string, so the output always
+        /// round-trips through `parse`.
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut separator = "";
+                let mut known: $type = 0;
+                for (flag, name) in Self::__FLAGS.iter().zip(Self::__NAMES) {
+                    if !flag.is_empty() && self.contains(*flag) {
+                        f.write_str(separator)?;
+                        f.write_str(name)?;
+                        separator = "|";
+                        known |= flag.0;
+                    }
+                }
+                let unknown = self.0 & !known;
+                if unknown != 0 {
+                    write!(f, "{}{:#x}", separator, unknown)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl $name {
+            $(
+                $(#[$inner])*
+                $vis const $flag: Self = Self($value);
+            )*
+
+            $(
+                #[doc = concat!("The group `", stringify!($($member)|+), "`.")]
+                $vis const $group: Self = Self(0 $(| Self::$member.0)+);
+            )*
+        }
+
+        // generated helpers go unused in most programs
+        #[allow(dead_code)]
+        impl $name {
+            /// Every declared flag, in declaration order.
+            const __FLAGS: &'static [Self] = &[$(Self::$flag),*];
+
+            /// The name of every declared flag, matching `__FLAGS`.
+            const __NAMES: &'static [&'static str] = &[$(stringify!($flag)),*];
+
+            /// Whether `from_bits` preserves undeclared bits.
+            const __RETAIN_UNKNOWN: bool = $retain;
+
+            /// Creates flags from raw bits.
+            ///
+            /// Returns `None` if `bits` contains undeclared bits, unless the
+            /// type was declared `with retain_unknown`, in which case they are
+            /// kept as is.
+            #[inline]
+            pub const fn from_bits(bits: $type) -> Option<Self> {
+                if Self::__RETAIN_UNKNOWN || bits & !Self::all().0 == 0 {
+                    Some(Self(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// Creates flags from raw bits, dropping undeclared bits.
+            #[inline]
+            pub const fn from_bits_truncate(bits: $type) -> Self {
+                Self(bits & Self::all().0)
+            }
+
+            /// Creates flags from raw bits, keeping undeclared bits.
+            #[inline]
+            pub const fn from_bits_retain(bits: $type) -> Self {
+                Self(bits)
+            }
+
+            /// Returns only the declared flags of self.
+            #[inline]
+            pub const fn known(self) -> Self {
+                Self(self.0 & Self::all().0)
+            }
+
+            /// Returns the raw bits of the declared flags of self.
+            #[inline]
+            pub const fn known_bits(self) -> $type {
+                self.known().0
+            }
+
+            /// Sets `bits` in self if they are all declared flags.
+            ///
+            /// Returns the undeclared bits as the error and leaves self
+            /// unchanged otherwise, even for types declared
+            /// `with retain_unknown`.
+            #[inline]
+            pub fn checked_insert(&mut self, bits: $type) -> Result<(), $type> {
+                let unknown = bits & !Self::all().0;
+                if unknown != 0 {
+                    return Err(unknown);
+                }
+                self.0 |= bits;
+                Ok(())
+            }
+
+            /// Returns the declared flag called `name`, if any.
+            pub fn from_name(name: &str) -> Option<Self> {
+                Self::__NAMES
+                    .iter()
+                    .position(|&declared| declared == name)
+                    .map(|index| Self::__FLAGS[index])
+            }
+
+            /// Parses the `|`-separated text form produced by `Display`.
+            ///
+            /// Each token is a declared flag name or a `0x`-prefixed hex value
+            /// for undeclared bits; surrounding whitespace is ignored. The empty
+            /// string parses to `empty()`. Returns `None` for unknown tokens.
+            pub fn parse(text: &str) -> Option<Self> {
+                let text = text.trim();
+                if text.is_empty() {
+                    return Some(Self::empty());
+                }
+                let mut bits: $type = 0;
+                for token in text.split('|') {
+                    let token = token.trim();
+                    let flag = match token.strip_prefix("0x") {
+                        Some(hex) => Self(<$type>::from_str_radix(hex, 16).ok()?),
+                        None => Self::from_name(token)?,
+                    };
+                    bits |= flag.0;
+                }
+                Some(Self(bits))
+            }
+
+            /// Returns an empty set of flags.
+            #[inline]
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// Returns the union of all declared flags.
+            #[inline]
+            pub const fn all() -> Self {
+                Self(0 $(| Self::$flag.0)*)
+            }
+
+            /// Returns true if no flags are set.
+            #[inline]
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Returns true if all flags in other are set in self.
+            #[inline]
+            pub const fn contains(self, other: Self) -> bool {
+                (self.0 & other.0) == other.0
+            }
+
+            /// Returns true if self has no flags set outside of allowed.
+            #[inline]
+            pub const fn contains_only(self, allowed: Self) -> bool {
+                (self.0 & !allowed.0) == 0
+            }
+
+            /// Returns true if self and other have no flags in common.
+            #[inline]
+            pub const fn is_disjoint(self, other: Self) -> bool {
+                (self.0 & other.0) == 0
+            }
+
+            /// Returns true if self and other have any flag in common.
+            #[inline]
+            pub const fn intersects(self, other: Self) -> bool {
+                (self.0 & other.0) != 0
+            }
+
+            /// Returns the flags set in self or other; the const `|`.
+            #[inline]
+            pub const fn union(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+
+            /// Returns the flags set in both self and other; the const `&`.
+            #[inline]
+            pub const fn intersection(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+
+            /// Returns the flags set in self but not in other.
+            #[inline]
+            pub const fn difference(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+
+            /// Returns the flags set in exactly one of self and other; the
+            /// const `^`.
+            #[inline]
+            pub const fn symmetric_difference(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+
+            /// Returns the declared flags not set in self.
+            ///
+            /// The complement is taken relative to [`all`](Self::all), so
+            /// undeclared bits are never set in the result. `!flags` is the
+            /// operator form.
+            #[inline]
+            pub const fn complement(self) -> Self {
+                Self(!self.0 & Self::all().0)
+            }
+
+            /// Returns self with the flags of other set.
+            #[inline]
+            #[must_use = "returns the new flags; self is unchanged"]
+            pub const fn insert(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+
+            /// Returns self with the flags of other cleared.
+            #[inline]
+            #[must_use = "returns the new flags; self is unchanged"]
+            pub const fn remove(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+
+            /// Returns self with the flags of other flipped.
+            #[inline]
+            #[must_use = "returns the new flags; self is unchanged"]
+            pub const fn toggle(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+
+            /// Returns the raw bits of the flags.
+            #[inline]
+            pub const fn bits(self) -> $type {
+                self.0
+            }
+
+            /// Returns the raw bits for in-place updates, e.g. a masked
+            /// read-modify-write of a register value.
+            ///
+            /// Any bit can be set this way, including undeclared ones, even
+            /// for strict types; use `known()` to drop them afterwards.
+            #[inline]
+            pub fn bits_mut(&mut self) -> &mut $type {
+                &mut self.0
+            }
+
+            /// Returns an iterator over the declared flags contained in self,
+            /// in declaration order.
+            pub fn iter(self) -> $crate::BitflagsIter<Self> {
+                const _: () = assert!($name::__FLAGS.len() <= 128, "bitflags! iteration supports at most 128 flags");
+
+                let mut selected = 0u128;
+                let mut i = 0;
+                while i < Self::__FLAGS.len() {
+                    if !Self::__FLAGS[i].is_empty() && self.contains(Self::__FLAGS[i]) {
+                        selected |= 1 << i;
+                    }
+                    i += 1;
+                }
+                $crate::BitflagsIter::new(Self::__FLAGS, selected)
+            }
+
+            /// Returns the declared flag whose value is exactly bit `index`,
+            /// or `None` if no declared flag occupies that bit.
+            pub const fn from_flag_index(index: u32) -> Option<Self> {
+                if index >= <$type>::BITS {
+                    return None;
+                }
+                let bit: $type = 1 << index;
+                let mut i = 0;
+                while i < Self::__FLAGS.len() {
+                    if Self::__FLAGS[i].0 == bit {
+                        return Some(Self::__FLAGS[i]);
+                    }
+                    i += 1;
+                }
+                None
+            }
+
+            /// Returns the number of declared flags contained in self.
+            #[inline]
+            pub fn count(self) -> usize {
+                self.iter().len()
+            }
+
+            /// Returns whether each declared flag is set, in declaration order.
+            pub const fn to_bools(self) -> [bool; $name::__FLAGS.len()] {
+                let mut bools = [false; $name::__FLAGS.len()];
+                let mut i = 0;
+                while i < bools.len() {
+                    let flag = Self::__FLAGS[i].0;
+                    bools[i] = flag != 0 && self.0 & flag == flag;
+                    i += 1;
+                }
+                bools
+            }
+
+            /// Creates flags from one boolean per declared flag, in
+            /// declaration order.
+            pub const fn from_bools(bools: [bool; $name::__FLAGS.len()]) -> Self {
+                let mut bits: $type = 0;
+                let mut i = 0;
+                while i < bools.len() {
+                    if bools[i] {
+                        bits |= Self::__FLAGS[i].0;
+                    }
+                    i += 1;
+                }
+                Self(bits)
+            }
+        }
+
+        impl From<$name> for [bool; $name::__FLAGS.len()] {
+            #[inline]
+            fn from(flags: $name) -> Self {
+                flags.to_bools()
+            }
+        }
+
+        impl From<[bool; $name::__FLAGS.len()]> for $name {
+            #[inline]
+            fn from(bools: [bool; $name::__FLAGS.len()]) -> Self {
+                Self::from_bools(bools)
+            }
+        }
+
+        impl core::ops::BitOr for $name {
+            type Output = Self;
+            #[inline]
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl core::ops::BitAnd for $name {
+            type Output = Self;
+            #[inline]
+            fn bitand(self, rhs: Self) -> Self {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl core::ops::BitXor for $name {
+            type Output = Self;
+            #[inline]
+            fn bitxor(self, rhs: Self) -> Self {
+                Self(self.0 ^ rhs.0)
+            }
+        }
+
+        impl core::ops::Not for $name {
+            type Output = Self;
+            #[inline]
+            fn not(self) -> Self {
+                self.complement()
+            }
+        }
+
+        impl $crate::Bitflags for $name {
+            type Bits = $type;
+
+            const WIDTH: u32 = <$type>::BITS;
+
+            #[inline]
+            fn bits(self) -> $type {
+                self.0
+            }
+
+            #[inline]
+            fn empty() -> Self {
+                Self(0)
+            }
+
+            #[inline]
+            fn contains(self, other: Self) -> bool {
+                (self.0 & other.0) == other.0
+            }
+
+            #[inline]
+            fn all() -> Self {
+                $name::all()
+            }
+        }
+
+        /// Combines all flag sets with `|`; an empty iterator sums to `empty()`.
+        impl core::iter::Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::empty(), |acc, flags| acc | flags)
+            }
+        }
+
+        impl<'a> core::iter::Sum<&'a $name> for $name {
+            fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                iter.copied().sum()
+            }
+        }
+
+        $crate::bitflags!(@default [$vis] $name [$($default)*]);
+    };
+    (
+        $(#[$outer:meta])*
+        $vis:vis struct $name:ident: $type:ty { $($body:tt)* }
+        $($options:tt)*
+    ) => {
+        $crate::bitflags!(
+            @options [$(#[$outer])* $vis struct $name: $type] { $($body)* } [false] [] $($options)*
+        );
+    };
+}
+
+/// Common interface of all flag types generated by [`bitflags!`].
+///
+/// Lets generic code accept any flags type and reason about its width,
+/// e.g. `assert!(F::WIDTH >= 12)` before storing twelve flags in it.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{bitflags, Bitflags};
+///
+/// bitflags! {
+///     struct Irq: u16 {
+///         const TIMER = 1 << 0;
+///         const UART = 1 << 1;
+///     }
+/// }
+///
+/// fn all_enabled<F: Bitflags + PartialEq>(enabled: F) -> bool {
+///     enabled == F::all()
+/// }
+///
+/// assert!(all_enabled(Irq::TIMER | Irq::UART));
+/// assert!(!all_enabled(Irq::UART));
+/// ```
+pub trait Bitflags: Copy {
+    /// The integer type backing the flags.
+    type Bits;
+
+    /// The number of bits available in [`Bits`](Self::Bits).
+    const WIDTH: u32;
+
+    /// Returns the raw bits of the flags.
+    fn bits(self) -> Self::Bits;
+
+    /// Returns an empty set of flags.
+    fn empty() -> Self;
+
+    /// Returns true if all flags in `other` are set in `self`.
+    fn contains(self, other: Self) -> bool;
+
+    /// Returns the union of all declared flags.
+    fn all() -> Self;
+}
+
+/// Defines bit flags exactly like [`bitflags!`], for types meant to be used
+/// through the [`Bitflags`] trait.
+///
+/// Every `bitflags!` type implements [`Bitflags`], including its `WIDTH`
+/// constant, so the two macros generate the same code; this name states
+/// the intent at the definition site.
+///
+/// # Examples
+/// ```rust
+/// use noir_macros_core::{bitflags_generic, Bitflags};
+///
+/// bitflags_generic! {
+///     pub struct Irq: u16 {
+///         const TIMER = 1 << 0;
+///         const UART = 1 << 1;
+///     }
+/// }
+///
+/// fn is_enabled<F: Bitflags>(enabled: F, irq: F) -> bool {
+///     assert!(F::WIDTH >= 16, "needs at least 16 interrupt lines");
+///     enabled.contains(irq)
+/// }
+///
+/// assert_eq!(<Irq as Bitflags>::WIDTH, 16);
+/// assert!(is_enabled(Irq::TIMER | Irq::UART, Irq::UART));
+/// ```
+#[macro_export]
+macro_rules! bitflags_generic {
+    ($($tokens:tt)*) => {
+        $crate::bitflags! { $($tokens)* }
+    };
+}
+
+/// Iterator over the declared flags set in a `bitflags!` value.
+///
+/// Returned by the generated `iter()` method. Flags are yielded in
+/// declaration order and the exact number of remaining flags is known
+/// up front, so `len()` can be used to preallocate.
+#[derive(Clone)]
+pub struct BitflagsIter<F: 'static> {
+    flags: &'static [F],
+    selected: u128,
+}
+
+impl<F: Copy> BitflagsIter<F> {
+    /// Creates an iterator over the entries of `flags` whose index bit is
+    /// set in `selected`.
+    #[doc(hidden)]
+    pub const fn new(flags: &'static [F], selected: u128) -> Self {
+        Self { flags, selected }
+    }
+}
+
+impl<F: Copy> Iterator for BitflagsIter<F> {
+    type Item = F;
+
+    fn next(&mut self) -> Option<F> {
+        if self.selected == 0 {
+            return None;
+        }
+        let index = self.selected.trailing_zeros() as usize;
+        self.selected &= self.selected - 1;
+        Some(self.flags[index])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.selected.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<F: Copy> ExactSizeIterator for BitflagsIter<F> {}
+
+impl<F: Copy> core::iter::FusedIterator for BitflagsIter<F> {}
+
+#[cfg(test)]
+mod tests {
+    //! Test module for noir_macros_core functionality.
+    //! 
+    //! This module contains comprehensive tests for all public APIs
+    //! and ensures thread-safety, memory safety, and correct behavior
+    //! of the static cell and assertion macros.
+    
+    use super::*;
+    use core::fmt::Write;
+
+    /// Tests basic static cell initialization and access.
+    /// 
+    /// # What This Test Teaches
+    /// - How to properly initialize a StaticCell
+    /// - Thread-safe access patterns
+    /// - Common initialization scenarios
+    /// 
+    /// # Key Concepts
+    /// 1. One-time initialization
+    /// 2. Thread safety
+    /// 3. Error handling
+    #[test]
+    fn test_static_cell() {
+        let cell = StaticCell::new();
+        assert!(cell.try_init(42));
+        assert_eq!(cell.get(), Some(&42));
+        assert!(!cell.try_init(24));
+        assert_eq!(cell.get(), Some(&42));
+    }
+
+    /// Tests type-keyed registration in a `TypeRegistry`.
+    #[test]
+    fn test_type_registry() {
+        struct Alpha(u8);
+        struct Beta(&'static str);
+
+        static ALPHA: Alpha = Alpha(7);
+        static BETA: Beta = Beta("beta");
+
+        let registry: TypeRegistry<2> = TypeRegistry::new();
+        assert!(registry.get::<Alpha>().is_none());
+        assert!(registry.register(&ALPHA));
+        assert!(registry.register(&BETA));
+        assert!(!registry.register(&ALPHA));
+
+        assert_eq!(registry.get::<Alpha>().map(|a| a.0), Some(7));
+        assert_eq!(registry.get::<Beta>().map(|b| b.0), Some("beta"));
+        assert!(registry.get::<u32>().is_none());
+    }
+
+    /// Tests that concurrent registrations of one type succeed only once.
+    #[test]
+    fn test_type_registry_contended() {
+        struct Service;
+
+        static REGISTRY: TypeRegistry<8> = TypeRegistry::new();
+        static SERVICE: Service = Service;
+
+        let handles: Vec<_> = (0..8).map(|_| std::thread::spawn(|| REGISTRY.register(&SERVICE))).collect();
+        let wins = handles.into_iter().map(|handle| handle.join().unwrap()).filter(|&won| won).count();
+        assert_eq!(wins, 1);
+        assert!(REGISTRY.get::<Service>().is_some());
+    }
+
+    /// Tests that `print_len!` reports the formatted output length.
+    #[test]
+    fn test_print_len() {
+        let expected = format!("value: {}\n", 1234);
+        let expected_len = expected.len();
+        assert_eq!(print_len!("value: {}\n", 1234), expected_len);
+        assert_eq!(print_len!(""), 0);
+    }
+
+    /// Tests that an injected value bypasses the production factory.
+    #[test]
+    fn test_injectable() {
+        injectable!(SERVICE: u32);
+
+        assert!(SERVICE.inject(7));
+        let mut factory_called = false;
+        let value = SERVICE.resolve(|| {
+            factory_called = true;
+            42
+        });
+        assert_eq!(*value, 7);
+        assert!(!factory_called);
+        assert!(!SERVICE.inject(8));
+
+        let fresh: Injectable<u32> = Injectable::new();
+        assert_eq!(*fresh.resolve(|| 42), 42);
+    }
+
+    /// Tests that prioritized static cells initialize lowest priority first.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_init_all_by_priority() {
+        use core::sync::atomic::AtomicUsize;
+
+        static ORDER: AtomicUsize = AtomicUsize::new(0);
+        fn record(id: usize) -> usize {
+            let position = ORDER.fetch_add(1, Ordering::Relaxed);
+            id * 10 + position
+        }
+
+        static_cell!(LATE, usize, priority = 30, init = record(3));
+        static_cell!(EARLY, usize, priority = 1, init = record(1));
+        static_cell!(MIDDLE, usize, priority = 20, init = record(2));
+
+        init_all_by_priority();
+        assert_eq!(EARLY.get(), Some(&10));
+        assert_eq!(MIDDLE.get(), Some(&21));
+        assert_eq!(LATE.get(), Some(&32));
+
+        init_all_by_priority();
+        assert_eq!(ORDER.load(Ordering::Relaxed), 3);
+    }
+
+    /// Tests that concurrent init walks evaluate each initializer once.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_init_all_concurrent() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn counted() -> usize {
+            CALLS.fetch_add(1, Ordering::SeqCst)
+        }
+
+        static_cell!(WALKED_PRIORITY, usize, priority = 5, init = counted());
+        static_cell!(WALKED_REGISTERED, usize, registered, init = counted());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    init_all_by_priority();
+                    init_all_registered();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+        assert!(WALKED_PRIORITY.get().is_some());
+        assert!(WALKED_REGISTERED.get().is_some());
+    }
+
+    /// Tests walking a simulated registration section on the host.
+    #[test]
+    fn test_init_registered_simulated_section() {
+        static CLOCK: StaticCell<u32> = StaticCell::new();
+        static UART: StaticCell<u32> = StaticCell::new();
+
+        static SECTION: [CellRecord; 2] = [
+            CellRecord {
+                name: "CLOCK",
+                init: || {
+                    CLOCK.try_init(48_000_000);
+                },
+            },
+            CellRecord {
+                name: "UART",
+                init: || {
+                    UART.try_init(CLOCK.get().map_or(0, |hz| hz / 416));
+                },
+            },
+        ];
+
+        init_registered(&SECTION);
+        assert_eq!(CLOCK.get(), Some(&48_000_000));
+        assert_eq!(UART.get(), Some(&115_384));
+        assert_eq!(SECTION.each_ref().map(|record| record.name), ["CLOCK", "UART"]);
+    }
+
+    /// Tests initializing cells registered in the `noir_cells` link section.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_init_all_registered() {
+        static_cell!(REGISTERED_LIMIT, u16, registered, init = 512);
+        static_cell!(REGISTERED_DEFAULT, u8, registered);
+
+        init_all_registered();
+        assert_eq!(REGISTERED_LIMIT.get(), Some(&512));
+        assert_eq!(REGISTERED_DEFAULT.get(), Some(&0));
+        assert!(registered_cells().any(|record| record.name == "REGISTERED_LIMIT"));
+        assert!(registered_cells().all(|record| !record.name.is_empty()));
+    }
+
+    /// Tests incrementing metrics and listing them with `dump_metrics`.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_metrics() {
+        metric!(TEST_RX_FRAMES);
+        metric!(TEST_TX_FRAMES);
+
+        TEST_RX_FRAMES.inc();
+        TEST_RX_FRAMES.inc();
+        TEST_TX_FRAMES.add(5);
+
+        let mut dumped = Vec::new();
+        dump_metrics(|name, value| dumped.push((alloc::string::String::from(name), value)));
+        dumped.sort();
+        assert_eq!(
+            dumped,
+            [(alloc::string::String::from("TEST_RX_FRAMES"), 2), (alloc::string::String::from("TEST_TX_FRAMES"), 5)]
+        );
+    }
+
+    /// Tests the aggregate initialization status of `static_cell!` cells.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_all_statics_initialized() {
+        static_cell!(HEALTH_CLOCK, u32);
+        static_cell!(HEALTH_UART, u32);
+
+        // cells declared by other tests are brought up by their init lists
+        init_all_by_priority();
+        init_all_registered();
+
+        let missing: Vec<_> = uninitialized_statics().collect();
+        assert!(missing.contains(&"HEALTH_CLOCK"));
+        assert!(missing.contains(&"HEALTH_UART"));
+        assert!(!all_statics_initialized());
+
+        HEALTH_CLOCK.try_init(48_000_000);
+        assert!(!uninitialized_statics().any(|name| name == "HEALTH_CLOCK"));
+        assert!(!all_statics_initialized());
+
+        HEALTH_UART.try_init(115_200);
+        assert!(all_statics_initialized());
+    }
+
+    /// Identifies test threads for reentrancy detection.
+    fn test_context_id() -> usize {
+        extern crate std;
+
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        std::thread_local!(static ID: usize = NEXT.fetch_add(1, Ordering::Relaxed));
+        ID.with(|id| *id)
+    }
+
+    /// Tests lazy initialization through `get_or_init`.
+    #[test]
+    fn test_static_cell_get_or_init() {
+        set_context_id_provider(test_context_id);
+
+        let cell = StaticCell::new();
+        assert_eq!(*cell.get_or_init(|| 4u32), 4);
+        assert_eq!(*cell.get_or_init(|| 5u32), 4);
+    }
+
+    /// Tests that a failed `get_or_try_init` leaves the cell empty for a retry.
+    #[test]
+    fn test_static_cell_get_or_try_init() {
+        set_context_id_provider(test_context_id);
+
+        let cell: StaticCell<u16> = StaticCell::new();
+        let probe = |present: bool| if present { Ok(0x2a) } else { Err("no device") };
+
+        assert_eq!(cell.get_or_try_init(|| probe(false)).map(|value| *value), Err("no device"));
+        assert!(!cell.is_initialized());
+
+        assert_eq!(cell.get_or_try_init(|| probe(true)).map(|value| *value), Ok(0x2a));
+        assert_eq!(cell.get_or_try_init(|| probe(false)).map(|value| *value), Ok(0x2a));
+        assert_eq!(cell.get(), Some(&0x2a));
+    }
+
+    /// Tests that racing `get_or_init` callers all observe the single stored value.
+    #[test]
+    fn test_static_cell_get_or_init_contended() {
+        const THREADS: usize = 4;
+        static START: AtomicBool = AtomicBool::new(false);
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        static CELL: StaticCell<Tracked> = StaticCell::new();
+
+        struct Tracked(usize);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        set_context_id_provider(test_context_id);
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    while !START.load(Ordering::Acquire) {
+                        core::hint::spin_loop();
+                    }
+                    let value = CELL.get_or_init(|| {
+                        CALLS.fetch_add(1, Ordering::SeqCst);
+                        Tracked(thread)
+                    });
+                    value.into_ref().0
+                })
+            })
+            .collect();
+        START.store(true, Ordering::Release);
+        let seen: Vec<usize> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+
+        // everyone got the winner's published value, and every losing value
+        // was dropped; losers wait on the cell state, never on the value
+        let winner = CELL.get().unwrap().0;
+        assert!(seen.iter().all(|&value| value == winner));
+        let calls = CALLS.load(Ordering::SeqCst);
+        assert!((1..=THREADS).contains(&calls));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), calls - 1);
+
+        // already initialized: the closure is not called again
+        CELL.get_or_init(|| unreachable!("initialized cell called its closure"));
+        assert_eq!(CALLS.load(Ordering::SeqCst), calls);
+    }
+
+    /// Tests passing the `Initialized` handle to code requiring initialization.
+    #[test]
+    fn test_initialized_handle() {
+        struct Config {
+            name: &'static str,
+            retries: u8,
+        }
+
+        fn describe(config: Initialized<'static, Config>) -> (&'static str, u8) {
+            (config.into_ref().name, config.retries)
+        }
+
+        set_context_id_provider(test_context_id);
+
+        static CONFIG: StaticCell<Config> = StaticCell::new();
+        let config = CONFIG.get_or_init(|| Config { name: "uart", retries: 3 });
+        assert_eq!(describe(config), ("uart", 3));
+        assert!(core::ptr::eq(config.into_ref(), CONFIG.get().unwrap()));
+    }
+
+    /// Tests that threads are told apart without a context id provider.
+    #[test]
+    fn test_thread_context_id() {
+        let here = thread_context_id();
+        assert!(here.is_some());
+        assert_eq!(thread_context_id(), here);
+        assert_ne!(std::thread::spawn(thread_context_id).join().unwrap(), here);
+    }
+
+    /// Tests that reentrant `get_or_init` panics instead of recursing.
+    #[test]
+    #[should_panic(expected = "reentrant initialization")]
+    fn test_static_cell_get_or_init_reentrant() {
+        set_context_id_provider(test_context_id);
+
+        static CELL: StaticCell<u32> = StaticCell::new();
+        CELL.get_or_init(|| *CELL.get_or_init(|| 1) + 1);
+    }
+
+    /// Tests `get_unchecked` after a confirmed initialization.
+    #[test]
+    fn test_static_cell_get_unchecked() {
+        let cell = StaticCell::new();
+        assert!(cell.try_init([1u16, 2, 3]));
+        assert!(cell.is_initialized());
+
+        let unchecked = unsafe { cell.get_unchecked() };
+        assert_eq!(unchecked, &[1, 2, 3]);
+        assert!(core::ptr::eq(unchecked, cell.get().unwrap()));
+    }
+
+    /// Tests the explicit-ordering `StaticCell` API on a single thread.
+    #[test]
+    fn test_static_cell_ordered() {
+        let cell = StaticCell::new();
+        unsafe {
+            assert_eq!(cell.get_ordered(Ordering::Relaxed), None);
+            assert!(cell.try_init_ordered(5u8, Ordering::Relaxed, Ordering::Relaxed));
+            assert!(!cell.try_init_ordered(6u8, Ordering::Relaxed, Ordering::Relaxed));
+            assert_eq!(cell.get_ordered(Ordering::Relaxed), Some(&5));
+        }
+        assert_eq!(cell.get(), Some(&5));
+    }
+
+    /// Tests the relaxed-ordering initialization fast path on a single thread.
+    #[test]
+    fn test_static_cell_get_or_init_racy() {
+        let cell = StaticCell::new();
+        let mut calls = 0;
+        unsafe {
+            assert_eq!(*cell.get_or_init_racy(|| { calls += 1; 11u32 }), 11);
+            assert_eq!(*cell.get_or_init_racy(|| { calls += 1; 12u32 }), 11);
+        }
+        assert_eq!(calls, 1);
+        assert_eq!(cell.get(), Some(&11));
+        assert!(!cell.try_init(13));
+    }
+
+    /// Tests resetting a static cell between two initialization cycles.
+    #[test]
+    fn test_static_cell_reset() {
+        static CELL: StaticCell<u32> = StaticCell::new();
+
+        assert!(CELL.try_init(1));
+        assert_eq!(CELL.get(), Some(&1));
+
+        unsafe { CELL.reset() };
+        assert_eq!(CELL.get(), None);
+
+        assert!(CELL.try_init(2));
+        assert_eq!(CELL.get(), Some(&2));
+    }
+
+    /// Tests the `Option`-style fallback accessors on `StaticCell`.
+    #[test]
+    fn test_static_cell_fallbacks() {
+        let cell: StaticCell<u32> = StaticCell::new();
+        assert_eq!(*cell.unwrap_or(&10), 10);
+        assert_eq!(cell.map_or(0, |v| v * 2), 0);
+
+        assert!(cell.try_init(3));
+        assert_eq!(*cell.unwrap_or(&10), 3);
+        assert_eq!(cell.map_or(0, |v| v * 2), 6);
+    }
+
+    /// Tests building a lookup table from a const fn.
+    #[test]
+    fn test_const_table() {
+        const fn square(index: usize) -> u32 {
+            (index * index) as u32
+        }
+
+        const SQUARES: [u32; 6] = const_table!(6, square);
+        assert_eq!(SQUARES, [0, 1, 4, 9, 16, 25]);
+    }
+
+    /// Tests compile-time base64 decoding and input validation.
+    #[test]
+    fn test_const_base64() {
+        const DECODED: &[u8] = const_base64!("SGVsbG8sIFdvcmxkIQ==");
+        assert_eq!(DECODED, b"Hello, World!");
+        assert_eq!(const_base64!(""), b"");
+        assert_eq!(const_base64!("YWJj"), b"abc");
+        assert_eq!(const_base64!("YWI="), b"ab");
+    }
+
+    /// Tests that characters outside the base64 alphabet are rejected.
+    #[test]
+    #[should_panic(expected = "invalid base64 character")]
+    fn test_base64_rejects_invalid_character() {
+        let _ = base64_decode::<3>(b"YW-j");
+    }
+
+    /// Tests `const_max!` and `const_min!` in const contexts.
+    #[test]
+    fn test_const_max_min() {
+        #[allow(dead_code)]
+        #[repr(C)]
+        union Frame {
+            header: [u8; 6],
+            word: u32,
+        }
+
+        const LARGEST: usize = const_max!(core::mem::size_of::<[u8; 6]>(), core::mem::size_of::<u32>());
+        const _: () = assert!(LARGEST == 6);
+        const _: () = assert!(const_min!(-3i32, 2) == -3);
+        const _: () = assert!(const_max!(7u8, 7) == 7);
+
+        assert_eq!(core::mem::size_of::<Frame>(), align_up(LARGEST, core::mem::align_of::<Frame>()));
+        assert_eq!(const_min!(u64::MAX, 0), 0);
+    }
+
+    /// Tests compile-time string comparison with `const_str_eq!`.
+    #[test]
+    fn test_const_str_eq() {
+        const SAME: bool = const_str_eq!("release", "release");
+        const _: () = assert!(SAME);
+        assert!(const_str_eq!("", ""));
+        assert!(!const_str_eq!("release", "relearn"));
+        assert!(!const_str_eq!("release", "releases"));
+        assert!(!const_str_eq!("", "a"));
+        assert!(str_eq("héllo", "héllo"));
+    }
+
+    /// Tests `const_fnv1a!` against published FNV-1a test vectors.
+    #[test]
+    fn test_const_fnv1a() {
+        const EMPTY: u64 = const_fnv1a!("");
+        assert_eq!(EMPTY, 0xcbf2_9ce4_8422_2325);
+        assert_eq!(const_fnv1a!("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(const_fnv1a!("foobar"), 0x8594_4171_f739_67e8);
+        assert_eq!(fnv1a_64(b"foobar"), const_fnv1a!("foobar"));
+    }
+
+    /// Tests that `select_backend!` initializes the active backend only.
+    #[test]
+    fn test_select_backend() {
+        static BACKEND: StaticCell<&'static str> = StaticCell::new();
+
+        assert!(select_backend!(BACKEND, {
+            target_os = "linux" => "linux",
+            not(target_os = "linux") => "other",
+        }));
+        assert_eq!(BACKEND.get(), Some(&if cfg!(target_os = "linux") { "linux" } else { "other" }));
+        assert!(!select_backend!(BACKEND, { test => "again" }));
+    }
+
+    /// Tests variant names generated by `enum_names!`.
+    #[test]
+    fn test_enum_names() {
+        enum_names! {
+            #[derive(Clone, Copy)]
+            enum State {
+                Idle,
+                Running = 4,
+                /// Finished
+                Done,
+            }
+        }
+
+        assert_eq!(State::Running.as_str(), "Running");
+        assert_eq!(State::Done as u8, 5);
+        let names: Vec<&str> = [State::Idle, State::Running, State::Done].iter().map(State::as_str).collect();
+        assert_eq!(names, ["Idle", "Running", "Done"]);
+    }
+
+    /// Tests the `bits!` helpers on single-bit and multi-bit values.
+    #[test]
+    fn test_bits_helpers() {
+        const HIGH: u8 = 0x80;
+        const _: () = assert!(matches!(bits!(bit_index(HIGH)), Some(7)));
+
+        assert_eq!(bits!(count_ones(0u32)), 0);
+        assert_eq!(bits!(count_ones(u64::MAX)), 64);
+        assert_eq!(bits!(count_ones(-1i16)), 16);
+        assert_eq!(bits!(leading_zeros(1u8)), 7);
+        assert_eq!(bits!(leading_zeros(0u128)), 128);
+        assert_eq!(bits!(trailing_zeros(0b1000u16)), 3);
+        assert_eq!(bits!(trailing_zeros(0u32)), 32);
+
+        assert_eq!(bits!(bit_index(1u32)), Some(0));
+        assert_eq!(bits!(bit_index(1u64 << 63)), Some(63));
+        assert_eq!(bits!(bit_index(i8::MIN)), Some(7));
+        assert_eq!(bits!(bit_index(0u8)), None);
+        assert_eq!(bits!(bit_index(0b1010u16)), None);
+        assert_eq!(bits!(bit_index(u128::MAX)), None);
+    }
+
+    /// Tests trait assertions with `static_assert_impl!`.
+    #[test]
+    fn test_static_assert_impl() {
+        static_assert_impl!(StaticCell<u32>: Send + Sync);
+        static_assert_impl!(Handle: Copy + Eq + core::hash::Hash + core::fmt::Debug);
+        static_assert_impl!([u8]: AsRef<[u8]>);
+        static_assert_impl!(Vec<&'static str>: Clone + Default);
+    }
+
+    /// Tests compile-time integer parsing with `parse_int!`.
+    #[test]
+    fn test_parse_int() {
+        const DECIMAL: u32 = parse_int!("42", u32);
+        assert_eq!(DECIMAL, 42);
+        assert_eq!(parse_int!("0x1F", u8), 0x1f);
+        assert_eq!(parse_int!("0xdead_beef", u32), 0xdead_beef);
+        assert_eq!(parse_int!("255", u8), u8::MAX);
+        assert_eq!(parse_int!("-1", i32), -1);
+        assert_eq!(parse_int!("-0x80", i8), i8::MIN);
+        assert_eq!(parse_int!("0xffffffffffffffffffffffffffffffff", u128), u128::MAX);
+    }
+
+    /// Tests that values that do not fit the target type are rejected.
+    #[test]
+    #[should_panic(expected = "integer literal out of range")]
+    fn test_parse_int_rejects_overflow() {
+        let _ = parse_int_literal("256", u8::MAX as u128, 0);
+    }
+
+    /// Tests that digits outside the radix are rejected.
+    #[test]
+    #[should_panic(expected = "invalid digit in integer literal")]
+    fn test_parse_int_rejects_invalid_digit() {
+        let _ = parse_int_literal("12a", u32::MAX as u128, 0);
+    }
+
+    /// Tests that literals made only of a sign, prefix or underscores are rejected.
+    #[test]
+    fn test_parse_int_rejects_missing_digits() {
+        extern crate std;
+
+        for text in ["", "-", "0x", "_", "0x_", "-_", "-0x__"] {
+            let result = std::panic::catch_unwind(|| parse_int_literal(text, u32::MAX as u128, 1));
+            assert!(result.is_err(), "{text:?} was accepted");
+        }
+    }
+
+    /// Tests that `lazy_cstr!` yields the null-terminated bytes of its literal.
+    #[test]
+    fn test_lazy_cstr() {
+        lazy_cstr!(GREETING = "hello");
+        lazy_cstr!(EMPTY = "");
+
+        assert_eq!(GREETING.get().to_bytes_with_nul(), b"hello\0");
+        assert!(core::ptr::eq(GREETING.get(), GREETING.get()));
+        assert_eq!(EMPTY.get().to_bytes_with_nul(), b"\0");
+    }
+
+    /// Tests capturing a panic message and reading it back.
+    #[test]
+    fn test_panic_buffer() {
+        extern crate std;
+
+        static LAST_PANIC: PanicBuffer<64> = PanicBuffer::new();
+        assert_eq!(LAST_PANIC.last_panic(), None);
+
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(std::boxed::Box::new(|info| {
+            // other tests may panic concurrently; only capture this one
+            if info.payload_as_str().is_some_and(|message| message.contains("timed out")) {
+                capture_panic!(LAST_PANIC, info);
+            }
+        }));
+        let result = std::panic::catch_unwind(|| panic!("sensor {} timed out", 3));
+        std::panic::set_hook(hook);
+
+        assert!(result.is_err());
+        let message = LAST_PANIC.last_panic().unwrap();
+        assert!(message.starts_with("panicked at src/lib.rs:"), "{}", message);
+        assert!(message.ends_with("sensor 3 timed out"), "{}", message);
+        assert!(!LAST_PANIC.capture(format_args!("second")));
+
+        let truncated = PanicBuffer::<4>::new();
+        assert!(truncated.capture(format_args!("{}", "hé€")));
+        assert_eq!(truncated.last_panic(), Some("hé"));
+    }
+
+    /// Tests that `lazy_slice!` computes its slice once and shares it.
+    #[test]
+    fn test_lazy_slice() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        lazy_slice!(POWERS: u64 = || {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            (0..10).map(|exponent| 1 << exponent).collect()
+        });
+
+        let first = POWERS.get();
+        assert_eq!(first.len(), 10);
+        assert_eq!(first[9], 512);
+        assert!(core::ptr::eq(first, POWERS.get()));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    /// Tests carving disjoint sub-slices out of a `lazy_arena!`.
+    #[test]
+    fn test_lazy_arena() {
+        lazy_arena!(SCRATCH: u32, 16);
+
+        let first = SCRATCH.alloc(10).unwrap();
+        let second = SCRATCH.alloc(6).unwrap();
+        assert_eq!((first.len(), second.len()), (10, 6));
+        assert_eq!(SCRATCH.remaining(), 0);
+        assert!(SCRATCH.alloc(1).is_none());
+        assert_eq!(SCRATCH.alloc(0).map(|empty| empty.len()), Some(0));
+
+        first.fill(1);
+        second.fill(2);
+        assert!(first.iter().all(|&value| value == 1));
+        assert!(second.iter().all(|&value| value == 2));
+
+        let first_end = first.as_ptr_range().end;
+        assert!(first_end <= second.as_ptr());
+    }
+
+    /// Tests that a `HardwareGuard` hands out a single token.
+    #[test]
+    fn test_hardware_guard() {
+        let guard = HardwareGuard::new();
+        assert!(!guard.is_acquired());
+        let _token: HardwareToken = guard.acquire();
+        assert!(guard.is_acquired());
+    }
+
+    /// Tests that acquiring a `HardwareGuard` twice panics.
+    #[test]
+    #[should_panic(expected = "hardware already initialized")]
+    fn test_hardware_guard_double_acquire() {
+        let guard = HardwareGuard::new();
+        let _token = guard.acquire();
+        guard.acquire();
+    }
+
+    /// Tests reading increasing ticks from a fake `Timebase` source.
+    #[test]
+    fn test_timebase() {
+        static TICKS: AtomicUsize = AtomicUsize::new(0);
+        fn fake_clock() -> u64 {
+            TICKS.fetch_add(10, Ordering::Relaxed) as u64
+        }
+
+        let time = Timebase::new();
+        assert_eq!(time.now(), 0);
+        assert!(time.init(fake_clock));
+        assert!(!time.init(|| u64::MAX));
+
+        let first = time.now();
+        let second = time.now();
+        assert!(second > first);
+        assert_eq!(second - first, 10);
+    }
+
+    /// Tests overriding a `ConfigCell` with a custom source.
+    #[test]
+    fn test_config_cell_source() {
+        let config: ConfigCell<u32> = ConfigCell::new();
+        assert!(config.get().is_none());
+
+        assert!(config.init_from(|| 400_000));
+        assert!(!config.init_from(|| unreachable!("source called after init")));
+        assert_eq!(config.get(), Some(&400_000));
+    }
+
+    /// Tests reading a `ConfigCell` from the environment.
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_config_cell_env() {
+        std::env::set_var("NOIR_TEST_CONFIG_BAUD", "9600");
+        let config: ConfigCell<u32> = ConfigCell::new();
+        assert!(config.init_from_env("NOIR_TEST_CONFIG_BAUD", 115_200));
+        assert_eq!(config.get(), Some(&9600));
+
+        let fallback: ConfigCell<u32> = ConfigCell::new();
+        assert!(fallback.init_from_env("NOIR_TEST_CONFIG_UNSET", 115_200));
+        assert_eq!(fallback.get(), Some(&115_200));
+    }
+
+    /// Tests that `critical_section!` calls enter and exit around its block.
+    #[test]
+    fn test_critical_section() {
+        static ENTERED: AtomicUsize = AtomicUsize::new(0);
+        static EXITED: AtomicUsize = AtomicUsize::new(0);
+
+        assert!(set_critical_section_impl(
+            || {
+                ENTERED.fetch_add(1, Ordering::SeqCst);
+            },
+            || {
+                EXITED.fetch_add(1, Ordering::SeqCst);
+            },
+        ));
+
+        let inside = critical_section!(|| (ENTERED.load(Ordering::SeqCst), EXITED.load(Ordering::SeqCst)));
+        assert_eq!(inside, (1, 0));
+        assert_eq!(ENTERED.load(Ordering::SeqCst), 1);
+        assert_eq!(EXITED.load(Ordering::SeqCst), 1);
+    }
+
+    /// Tests that `FallibleCell` caches both successful and failed attempts.
+    #[test]
+    fn test_fallible_cell() {
+        set_context_id_provider(test_context_id);
+
+        let ok: FallibleCell<u32, &str> = FallibleCell::new();
+        assert_eq!(ok.get(), None);
+        assert_eq!(ok.try_init_with(|| Ok(48)), Ok(&48));
+        assert_eq!(ok.try_init_with(|| Err("late failure")), Ok(&48));
+        assert_eq!(ok.get(), Some(Ok(&48)));
+
+        let failed: FallibleCell<u32, &str> = FallibleCell::new();
+        assert_eq!(failed.try_init_with(|| Err("crc mismatch")), Err(&"crc mismatch"));
+        assert_eq!(failed.try_init_with(|| unreachable!("failure was not cached")), Err(&"crc mismatch"));
+        assert_eq!(failed.get(), Some(Err(&"crc mismatch")));
+    }
+
+    /// Tests that `BufferedLogger` batches lines into single handler calls.
+    #[test]
+    fn test_buffered_logger() {
+        use alloc::string::String;
+
+        static FLUSHED: SpinLock<Vec<String>> = SpinLock::new(Vec::new());
+        let logger: BufferedLogger<24> = BufferedLogger::new();
+
+        // lines are kept until a handler exists
+        logger.log(format_args!("early"));
+        logger.flush();
+        assert!(logger.init(|text| FLUSHED.lock().push(text.into())));
+
+        logger.log(format_args!("temp={}", 21));
+        logger.log(format_args!("ok"));
+        assert!(FLUSHED.lock().is_empty());
+        logger.flush();
+        assert_eq!(*FLUSHED.lock(), ["early\ntemp=21\nok\n"]);
+
+        // a full buffer flushes before taking the next line
+        logger.log(format_args!("0123456789"));
+        logger.log(format_args!("abcdefghij"));
+        logger.log(format_args!("next"));
+        logger.flush();
+        logger.flush();
+        assert_eq!(FLUSHED.lock()[1..], ["0123456789\nabcdefghij\n", "next\n"]);
+    }
+
+    /// Tests that an oversized line is truncated rather than lost.
+    #[test]
+    fn test_buffered_logger_oversized_line() {
+        use alloc::string::String;
+
+        static FLUSHED: SpinLock<Vec<String>> = SpinLock::new(Vec::new());
+        let logger: BufferedLogger<8> = BufferedLogger::new();
+
+        // kept until the handler is registered
+        logger.log(format_args!("0123456789"));
+        assert!(logger.init(|text| FLUSHED.lock().push(text.into())));
+        logger.flush();
+        assert_eq!(*FLUSHED.lock(), ["01234567"]);
+
+        // emitted right away once the handler exists
+        logger.log(format_args!("abcdefghij"));
+        logger.flush();
+        assert_eq!(*FLUSHED.lock(), ["01234567", "abcdefgh"]);
+    }
+
+    /// Tests that `format_cache!` formats each variant's string only once.
+    #[test]
+    fn test_format_cache() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone, Copy)]
+        enum Status {
+            Idle,
+            Busy,
+            Fault,
+        }
+
+        format_cache! {
+            fn status_message(status: Status) {
+                Status::Idle => {
+                    CALLS.fetch_add(1, Ordering::Relaxed);
+                    alloc::format!("idle after {} ms", 250)
+                },
+                Status::Busy | Status::Fault => {
+                    CALLS.fetch_add(1, Ordering::Relaxed);
+                    alloc::format!("not ready: {}", "busy")
+                },
+            }
+        }
+
+        for _ in 0..3 {
+            assert_eq!(status_message(Status::Idle), "idle after 250 ms");
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+
+        let busy = status_message(Status::Busy);
+        assert_eq!(status_message(Status::Fault), "not ready: busy");
+        assert!(core::ptr::eq(busy, status_message(Status::Busy)));
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    }
+
+    /// Tests reclaiming the value with `take` and initializing again.
+    #[test]
+    fn test_static_cell_take() {
+        let mut cell = StaticCell::new();
+        assert_eq!(cell.take(), None);
+
+        assert!(cell.try_init("first"));
+        assert_eq!(cell.take(), Some("first"));
+        assert!(!cell.is_initialized());
+        assert_eq!(cell.get(), None);
+        assert_eq!(cell.take(), None);
+
+        assert!(cell.try_init("second"));
+        assert_eq!(cell.get(), Some(&"second"));
+    }
+
+    /// Tests that a local cell drops its value exactly once.
+    #[test]
+    fn test_static_cell_drops_value() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted;
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
             }
         }
-    }};
-}
 
-/// Internal function to handle actual printing.
-#[doc(hidden)]
-pub fn _print(s: &str) {
-    // Implementation depends on target platform
-    #[cfg(target_arch = "wasm32")]
-    {
-        // Web assembly implementation
-        extern "C" {
-            fn console_log(ptr: *const u8, len: usize);
+        {
+            let cell = StaticCell::new();
+            assert!(cell.try_init(Counted));
+            assert!(!cell.try_init(Counted));
+            // the rejected value is dropped right away
+            assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+        }
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+
+        drop(StaticCell::<Counted>::new());
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+    }
+
+    /// Tests mutating an initialized cell through `get_mut`.
+    #[test]
+    fn test_static_cell_get_mut() {
+        let mut cell: StaticCell<Vec<u8>> = StaticCell::new();
+        assert!(cell.get_mut().is_none());
+
+        assert!(cell.try_init(Vec::from([1, 2])));
+        cell.get_mut().unwrap().push(3);
+        assert_eq!(cell.get().unwrap(), &[1, 2, 3]);
+    }
+
+    /// Tests `is_initialized` before and after initialization attempts.
+    #[test]
+    fn test_static_cell_is_initialized() {
+        let cell = StaticCell::new();
+        assert!(!cell.is_initialized());
+        assert!(cell.try_init(1u8));
+        assert!(cell.is_initialized());
+        assert!(!cell.try_init(2u8));
+        assert!(cell.is_initialized());
+    }
+
+    /// Tests that `set` hands back exactly the rejected value.
+    #[test]
+    fn test_static_cell_set() {
+        let cell: StaticCell<Vec<u8>> = StaticCell::new();
+
+        assert_eq!(cell.set(Vec::from([1, 2])), Ok(()));
+        let rejected = Vec::from([3, 4, 5]);
+        let ptr = rejected.as_ptr();
+        let returned = cell.set(rejected).unwrap_err();
+        assert_eq!(returned, [3, 4, 5]);
+        assert_eq!(returned.as_ptr(), ptr);
+        assert_eq!(cell.get().unwrap(), &[1, 2]);
+        assert!(!cell.try_init(returned));
+    }
+
+    /// Tests that `init_or_get` hands back the existing value on repeated init.
+    #[test]
+    fn test_init_or_get() {
+        let cell: StaticCell<&str> = StaticCell::new();
+
+        assert_eq!(cell.init_or_get("primary"), Ok(()));
+        assert_eq!(cell.init_or_get("fallback"), Err(&"primary"));
+        assert_eq!(cell.get(), Some(&"primary"));
+    }
+
+    /// Tests sending and receiving through a `Handoff`.
+    #[test]
+    fn test_handoff() {
+        use alloc::string::String;
+
+        let handoff = Handoff::new();
+        assert_eq!(handoff.recv(), None);
+        assert_eq!(handoff.send(String::from("config")), Ok(()));
+        assert_eq!(handoff.send(String::from("other")), Err(String::from("other")));
+        assert_eq!(handoff.recv().as_deref(), Some("config"));
+        assert_eq!(handoff.recv(), None);
+        assert_eq!(handoff.send(String::from("late")), Err(String::from("late")));
+
+        // a value that is never received is dropped with the channel
+        let shared = alloc::sync::Arc::new(0u8);
+        let unreceived = Handoff::new();
+        assert!(unreceived.send(shared.clone()).is_ok());
+        drop(unreceived);
+        assert_eq!(alloc::sync::Arc::strong_count(&shared), 1);
+    }
+
+    /// Tests that `format!` sizes its buffer from the format string length.
+    #[test]
+    fn test_format_capacity_hint() {
+        assert_eq!(format!("short {}", 1), "short 1");
+        assert_eq!(LAST_BUFFER_CAPACITY.with(|last| last.get()), 64);
+
+        let long = format!("{}: 0123456789012345678901234567890123456789012345678901234567890123456789", 'n');
+        assert_eq!(long.len(), 73);
+        assert_eq!(LAST_BUFFER_CAPACITY.with(|last| last.get()), 148);
+
+        assert_eq!(format_capacity_hint(5), 64);
+        assert_eq!(format_capacity_hint(100), 200);
+        assert_eq!(format_capacity_hint(usize::MAX), DEFAULT_BUFFER_SIZE);
+    }
+
+    /// Tests that `lazy_format!` formats only when written.
+    #[test]
+    fn test_lazy_format() {
+        use core::fmt::Write as _;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn expensive() -> u32 {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            42
+        }
+
+        let label = alloc::string::String::from("sensor");
+        let message = lazy_format!("{}={}", label, expensive());
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+
+        let mut out = ArrayBuffer::<32>::new();
+        write!(out, "{}", message).unwrap();
+        assert_eq!(out.as_str(), "sensor=42");
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+
+        let _unused = lazy_format!("{}", expensive());
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    /// Tests writing lines to a mock UART with `uart_println!`.
+    #[test]
+    fn test_uart_println() {
+        struct MockUart {
+            tx: alloc::string::String,
+        }
+
+        impl core::fmt::Write for MockUart {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.tx.push_str(s);
+                Ok(())
+            }
         }
-        unsafe {
-            console_log(s.as_ptr(), s.len());
+
+        fn report(uart: &mut MockUart) -> core::fmt::Result {
+            uart_println!(uart, "done")
         }
+
+        let mut uart = MockUart { tx: alloc::string::String::new() };
+        assert_eq!(uart_println!(uart, "temp={}C", 21), Ok(()));
+        assert_eq!(uart_println!(&mut uart), Ok(()));
+        assert_eq!(report(&mut uart), Ok(()));
+        assert_eq!(uart.tx, "temp=21C\n\ndone\n");
+
+        // a failed write does not emit the newline
+        let mut full = ArrayBuffer::<4>::new();
+        assert!(uart_println!(full, "{}", "12345").is_err());
+        assert_eq!(full.as_str(), "1234");
     }
-    
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        // Default implementation using core::fmt::Write
-        use core::fmt::Write;
-        struct Stdout;
-        
-        impl Write for Stdout {
+
+    /// Tests formatting into a custom `Write` implementor with `fmt_to!`.
+    #[test]
+    fn test_fmt_to() {
+        struct Fixed {
+            bytes: [u8; 8],
+            len: usize,
+        }
+
+        impl core::fmt::Write for Fixed {
             fn write_str(&mut self, s: &str) -> core::fmt::Result {
-                extern "C" {
-                    fn putchar(c: i32) -> i32;
-                }
-                for byte in s.bytes() {
-                    unsafe {
-                        putchar(byte as i32);
-                    }
+                let end = self.len + s.len();
+                if end > self.bytes.len() {
+                    return Err(core::fmt::Error);
                 }
+                self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+                self.len = end;
                 Ok(())
             }
         }
-        
-        let mut stdout = Stdout;
-        let _ = stdout.write_str(s);
+
+        fn report(out: &mut Fixed, code: u8) -> core::fmt::Result {
+            fmt_to!(out, "E{:02}", code)?;
+            fmt_to!(out, "!")
+        }
+
+        let mut out = Fixed { bytes: [0; 8], len: 0 };
+        assert_eq!(report(&mut out, 7), Ok(()));
+        assert_eq!(&out.bytes[..out.len], b"E07!");
+        assert_eq!(fmt_to!(out, "{}", "overflow"), Err(core::fmt::Error));
     }
-}
 
-/// Prints formatted text to the standard output, with a newline.
-/// 
-/// # Understanding println!
-/// This macro extends the `print!` macro by automatically adding a newline
-/// at the end of the output. It's essential for formatted console output
-/// in no_std environments.
-/// 
-/// # How It Works
-/// 1. Formats the text using the same rules as `print!`
-/// 2. Appends a newline character (`\n`)
-/// 3. Writes to the output in a single operation
-/// 
-/// # Examples
-/// ```rust
-/// use noir_macros_core::println;
-/// use noir_macros_core::vec;
-///
-/// // Basic usage
-/// println!("Hello, World!");
-/// 
-/// // With formatting
-/// let name = "Rust";
-/// println!("Learning {}", name);
-/// 
-/// // Multiple values
-/// let (x, y) = (10, 20);
-/// println!("Point: ({}, {})", x, y);
-/// 
-/// // Debug formatting
-/// let data = vec![1, 2, 3];
-/// println!("Data: {:?}", data);
-/// ```
-/// 
-/// # Common Use Cases
-/// 1. Debug output
-/// 2. User interaction
-/// 3. Logging information
-/// 4. Progress reporting
-/// 
-/// # Best Practices
-/// 1. Use for human-readable output
-/// 2. Consider buffering for many prints
-/// 3. Use debug format `{:?}` for complex types
-/// 4. Avoid in performance-critical loops
-#[macro_export]
-macro_rules! println {
-    () => {
-        $crate::print!("\n")
-    };
-    ($($arg:tt)*) => {
-        $crate::print!("{}\n", format_args!($($arg)*))
-    };
-}
+    /// Tests the `core::fmt`-free numeric writers of `Buffer` on edge values.
+    #[test]
+    fn test_buffer_numeric_writers() {
+        fn written(write: impl FnOnce(&mut Buffer) -> core::fmt::Result) -> alloc::string::String {
+            let mut buffer = Buffer::with_capacity(64);
+            write(&mut buffer).unwrap();
+            let len = *buffer.pos.get_mut();
+            alloc::string::String::from(core::str::from_utf8(&buffer.buf.get_mut()[..len]).unwrap())
+        }
 
-/// Internal helper struct for print macro.
-/// 
-/// This type implements `fmt::Write` to enable formatted printing
-/// in no_std environments. It's used internally by the print
-/// macro implementation.
-/// 
-/// # Implementation Notes
-/// - Provides a no-op implementation of `write_str`
-/// - Used for compile-time format string validation
-#[doc(hidden)]
-pub struct PrintWrapper;
+        for n in [0, 7, 10, 99, 100, 12_345, u64::MAX] {
+            assert_eq!(written(|b| b.write_u64(n)), alloc::format!("{}", n));
+        }
+        for n in [0, -1, 42, -100, i64::MAX, i64::MIN] {
+            assert_eq!(written(|b| b.write_i64(n)), alloc::format!("{}", n));
+        }
 
-impl core::fmt::Write for PrintWrapper {
-    /// Implements the write_str method required by fmt::Write.
-    /// This is a no-op implementation used only for compile-time
-    /// format string validation.
-    fn write_str(&mut self, _s: &str) -> core::fmt::Result {
-        Ok(())
+        assert_eq!(written(|b| b.write_f64(0.0, 2)), "0.00");
+        assert_eq!(written(|b| b.write_f64(-0.0, 1)), "0.0");
+        assert_eq!(written(|b| b.write_f64(12.3456, 3)), "12.346");
+        assert_eq!(written(|b| b.write_f64(-1.5, 0)), "-2");
+        assert_eq!(written(|b| b.write_f64(0.999, 2)), "1.00");
+        assert_eq!(written(|b| b.write_f64(2.05, 9)), "2.050000000");
+        assert_eq!(written(|b| b.write_f64(1e-3, 4)), "0.0010");
+        assert_eq!(written(|b| b.write_f64(1e20, 1)), "100000000000000000000.0");
+        assert_eq!(written(|b| b.write_f64(f64::NAN, 2)), "NaN");
+        assert_eq!(written(|b| b.write_f64(f64::NEG_INFINITY, 2)), "-inf");
     }
-}
 
-/// The default size for new buffers.
-pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+    /// Tests that `format_bounded!` errors once the output exceeds its limit.
+    #[test]
+    fn test_format_bounded() {
+        assert_eq!(format_bounded!(32, "{}-{}", "a", 1).unwrap().as_str(), "a-1");
+        assert_eq!(format_bounded!(8, "{}", "12345678").unwrap().as_str(), "12345678");
+        assert!(format_bounded!(8, "{}", "123456789").is_err());
+        assert!(format_bounded!(16, "{:>64}", "long").is_err());
 
-/// The maximum allowed buffer size.
-pub const MAX_BUFFER_SIZE: usize = 1024 * 1024;
+        // each result owns its text, so earlier ones survive later calls
+        let kept: Vec<_> = (0..3).map(|i| format_bounded!(8, "n{}", i).unwrap()).collect();
+        let texts: Vec<&str> = kept.iter().map(ArrayBuffer::as_str).collect();
+        assert_eq!(texts, ["n0", "n1", "n2"]);
+    }
 
-/// A buffer for storing formatted strings with configurable size.
-#[doc(hidden)]
-pub struct Buffer {
-    pub buf: UnsafeCell<Vec<u8>>,
-    pub pos: UnsafeCell<usize>,
-    pub capacity: usize,
-}
+    /// Tests seeding and the deterministic sequence of `GlobalSeed`.
+    #[test]
+    fn test_global_seed() {
+        let seed = GlobalSeed::new();
+        assert!(seed.seed(1));
+        assert!(!seed.seed(2));
+        assert_eq!(seed.get(), 1);
 
-impl Buffer {
-    /// Creates a new buffer with the default capacity.
-    pub fn new() -> Self {
-        Self::with_capacity(DEFAULT_BUFFER_SIZE)
+        assert_eq!(seed.next_u64(), 0x4082_2041);
+        assert_eq!(seed.next_u64(), 0x1000_4106_0c01_1441);
+        assert_eq!(seed.next_u64(), 0x9b1e_842f_6e86_2629);
     }
 
-    /// Creates a new buffer with the specified capacity.
-    /// 
-    /// # Safety
-    /// The capacity must be less than or equal to MAX_BUFFER_SIZE.
-    pub fn with_capacity(capacity: usize) -> Self {
-        assert!(capacity <= MAX_BUFFER_SIZE, "Buffer capacity exceeds maximum allowed size");
-        Self {
-            buf: UnsafeCell::new(Vec::with_capacity(capacity)),
-            pos: UnsafeCell::new(0),
-            capacity,
+    /// Tests that reading an unseeded `GlobalSeed` panics.
+    #[test]
+    #[should_panic(expected = "GlobalSeed read before it was seeded")]
+    fn test_global_seed_unseeded() {
+        GlobalSeed::new().next_u64();
+    }
+
+    /// Tests that `unique_id!` yields distinct increasing values.
+    #[test]
+    fn test_unique_id() {
+        let first = unique_id!();
+        let second = unique_id!();
+        assert_ne!(first, second);
+        assert!(second > first);
+    }
+
+    /// Tests that `Watchdog::pet` calls the registered routine.
+    #[test]
+    fn test_watchdog() {
+        use core::sync::atomic::AtomicUsize;
+
+        static PETS: AtomicUsize = AtomicUsize::new(0);
+        fn pet() {
+            PETS.fetch_add(1, Ordering::Relaxed);
         }
+
+        let watchdog = Watchdog::new();
+        watchdog.pet();
+        assert_eq!(PETS.load(Ordering::Relaxed), 0);
+
+        assert!(watchdog.init(pet));
+        watchdog.pet();
+        watchdog.pet();
+        assert_eq!(PETS.load(Ordering::Relaxed), 2);
+        assert!(!watchdog.init(pet));
     }
 
-    /// Returns true if the buffer has enough space for additional bytes.
-    #[inline]
-    pub fn has_capacity(&self, additional: usize) -> bool {
-        unsafe { *self.pos.get() + additional <= self.capacity }
+    /// Tests formatting into separate caller-owned buffers.
+    #[test]
+    fn test_format_with() {
+        let mut first_buf = Buffer::with_capacity(16);
+        let mut second_buf = Buffer::with_capacity(16);
+
+        let first = format_with!(&mut first_buf, "first {}", 1);
+        let second = format_with!(&mut second_buf, "second {}", 2);
+        assert_eq!(first, "first 1");
+        assert_eq!(second, "second 2");
     }
 
-    /// Attempts to grow the buffer to accommodate more data.
-    /// Returns true if successful, false if the new size would exceed MAX_BUFFER_SIZE.
-    pub fn try_grow(&self, required: usize) -> bool {
-        unsafe {
-            let current_pos = *self.pos.get();
-            let new_size = (current_pos + required).next_power_of_two();
-            
-            if new_size <= MAX_BUFFER_SIZE {
-                let buf = &mut *self.buf.get();
-                buf.reserve(new_size - buf.len());
-                buf.resize(new_size, 0);
-                true
-            } else {
-                false
+    /// Tests that `Lazy` runs its initializer once.
+    #[test]
+    fn test_lazy() {
+        use core::sync::atomic::AtomicUsize;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static VALUE: Lazy<u32> = Lazy::new(|| {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            9
+        });
+
+        assert_eq!(*VALUE.force(), 9);
+        assert_eq!(*VALUE.force(), 9);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    /// Tests that racing `force` calls run the initializer exactly once.
+    #[test]
+    fn test_lazy_force_contended() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static VALUE: Lazy<u32> = Lazy::new(|| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            5
+        });
+
+        let handles: Vec<_> = (0..4).map(|_| std::thread::spawn(|| *VALUE.force())).collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 5);
+        }
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    /// Tests that forcing a `Lazy` from its own initializer panics.
+    #[test]
+    #[should_panic(expected = "reentrant initialization")]
+    fn test_lazy_force_reentrant() {
+        set_context_id_provider(test_context_id);
+
+        static VALUE: Lazy<u32> = Lazy::new(|| *VALUE.force() + 1);
+        VALUE.force();
+    }
+
+    /// Tests that dereferencing a `Lazy` initializes it exactly once.
+    #[test]
+    fn test_lazy_deref() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static TABLE: Lazy<[u32; 4]> = Lazy::new(|| {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            [0, 1, 4, 9]
+        });
+
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+        assert_eq!(TABLE[3], 9);
+        assert_eq!(*TABLE, [0, 1, 4, 9]);
+        assert!(core::ptr::eq(&*TABLE, TABLE.force()));
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    /// Tests that `Lazy::get` peeks without running the initializer.
+    #[test]
+    fn test_lazy_get() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let value: Lazy<u32, _> = Lazy::new(|| {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            7
+        });
+
+        assert_eq!(value.get(), None);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+        assert_eq!(*value.force(), 7);
+        assert_eq!(value.get(), Some(&7));
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    /// Tests replacing a forced `Lazy` value.
+    #[cfg(feature = "reinit")]
+    #[test]
+    fn test_lazy_reinit() {
+        use alloc::sync::Arc;
+
+        let config: Lazy<Arc<&str>> = Lazy::new(|| Arc::new("initial"));
+        assert_eq!(**config.current(), "initial");
+        let before = config.force();
+
+        let reloaded = Arc::new("reloaded");
+        config.reinit(reloaded.clone());
+        assert_eq!(**config.current(), "reloaded");
+        assert_eq!(**before, "initial");
+
+        // the replaced value is dropped, not leaked
+        config.reinit(Arc::new("again"));
+        assert_eq!(**config.current(), "again");
+        assert_eq!(Arc::strong_count(&reloaded), 1);
+    }
+
+    /// Tests content deduplication and index stability in `ByteInterner`.
+    #[test]
+    fn test_byte_interner() {
+        static HEADER: [u8; 3] = [0xaa, 0x55, 0x01];
+        static HEADER_COPY: [u8; 3] = [0xaa, 0x55, 0x01];
+
+        let interner: ByteInterner<3> = ByteInterner::new();
+        let header = interner.intern(&HEADER);
+        let empty = interner.intern(&[]);
+        let trailer = interner.intern(b"\r\n");
+
+        assert_eq!(interner.intern(&HEADER_COPY), header);
+        assert_eq!(interner.intern(&[]), empty);
+        assert_eq!(interner.intern(b"\r\n"), trailer);
+        assert_eq!([header, empty, trailer], [0, 1, 2]);
+        assert_eq!(interner.resolve(header), Some(&HEADER[..]));
+        assert_eq!(interner.resolve(trailer), Some(&b"\r\n"[..]));
+        assert_eq!(interner.resolve(3), None);
+    }
+
+    /// Tests looking up present and missing keys in a `StaticDict`.
+    #[test]
+    fn test_static_dict() {
+        let dict: StaticDict<u16, &str, 3> = StaticDict::new();
+        assert_eq!(dict.get(&0x10), None);
+
+        assert!(dict.init([(0x10, "sensor"), (0x20, "eeprom"), (0x30, "rtc")]));
+        assert!(!dict.init([(0x10, "other"), (0x20, "other"), (0x30, "other")]));
+
+        assert_eq!(dict.get(&0x10), Some(&"sensor"));
+        assert_eq!(dict.get(&0x30), Some(&"rtc"));
+        assert_eq!(dict.get(&0x40), None);
+    }
+
+    /// Tests string deduplication and index stability in `StringInterner`.
+    #[test]
+    fn test_string_interner() {
+        let interner: StringInterner<3> = StringInterner::new();
+        let a = interner.intern("alpha");
+        let b = interner.intern("beta");
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("alpha"), a);
+        assert_eq!(interner.intern("beta"), b);
+
+        assert_eq!(interner.resolve(a), Some("alpha"));
+        assert_eq!(interner.resolve(b), Some("beta"));
+        assert_eq!(interner.resolve(2), None);
+        assert_eq!(interner.resolve(3), None);
+    }
+
+    /// Tests that deferred blocks run at scope exit, in reverse order.
+    #[test]
+    fn test_defer() {
+        use core::cell::Cell;
+
+        let log = Cell::new(0u32);
+        {
+            defer! { log.set(log.get() * 10 + 1); }
+            defer! { log.set(log.get() * 10 + 2); }
+            assert_eq!(log.get(), 0);
+        }
+        assert_eq!(log.get(), 21);
+
+        fn early_return(ran: &Cell<bool>) -> u8 {
+            defer! { ran.set(true); }
+            if !ran.get() {
+                return 1;
             }
+            2
         }
+        let ran = Cell::new(false);
+        assert_eq!(early_return(&ran), 1);
+        assert!(ran.get());
     }
-}
 
-// SAFETY: Access to Buffer is synchronized through StaticCell and we ensure
-// single-threaded access during writes through atomic operations.
-// The Buffer is effectively immutable between writes due to the StaticCell
-// synchronization, and all modifications are done through UnsafeCell which
-// provides interior mutability in a controlled manner.
-unsafe impl Sync for Buffer {}
+    /// Tests building fixed-capacity vectors with `hvec!`.
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_hvec() {
+        let list = hvec![3; 10, 20, 30];
+        assert_eq!(list.as_slice(), &[10, 20, 30]);
 
-impl core::fmt::Write for Buffer {
-    fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        let bytes = s.as_bytes();
-        let pos = unsafe { *self.pos.get() };
-        
-        if !self.has_capacity(bytes.len()) && !self.try_grow(bytes.len()) {
-            return Err(core::fmt::Error);
+        let repeated = hvec![4; 'x'; 4];
+        assert_eq!(repeated.as_slice(), &['x'; 4]);
+
+        let empty: heapless::Vec<u8, 2> = hvec![2];
+        assert!(empty.is_empty());
+    }
+
+    /// Tests that `hvec!` rejects more elements than its capacity.
+    #[cfg(feature = "heapless")]
+    #[test]
+    #[should_panic(expected = "hvec! element count exceeds capacity")]
+    fn test_hvec_overflow() {
+        let _ = hvec![2; 1, 2, 3];
+    }
+
+    /// Tests that `hvec!` rejects a repeat count larger than its capacity.
+    #[cfg(feature = "heapless")]
+    #[test]
+    #[should_panic(expected = "hvec! element count exceeds capacity")]
+    fn test_hvec_repeat_overflow() {
+        let _ = hvec![2; 0u8; 3];
+    }
+
+    /// Tests that a `StaticMut` reference can only be taken once.
+    #[test]
+    fn test_static_mut_take_once() {
+        static COUNTER: StaticMut<u32> = StaticMut::new(7);
+
+        assert!(!COUNTER.is_taken());
+        let counter = COUNTER.take().unwrap();
+        *counter += 1;
+        assert_eq!(*counter, 8);
+        assert!(COUNTER.is_taken());
+        assert!(COUNTER.take().is_none());
+    }
+
+    /// Tests that fixture setup runs before and teardown after the body.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_fixtures() {
+        static LOG: SpinLock<Vec<&str>> = SpinLock::new(Vec::new());
+
+        fixture! {
+            setup: || LOG.lock().push("setup"),
+            teardown: || LOG.lock().push("teardown"),
+        }
+
+        let result = with_fixtures(|| {
+            LOG.lock().push("body");
+            5
+        });
+        assert_eq!(result, 5);
+        assert_eq!(*LOG.lock(), ["setup", "body", "teardown"]);
+    }
+
+    /// Tests that self-tests report both passing and failing checks.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_self_tests() {
+        use alloc::string::String;
+
+        self_test!(fn check_ram() -> Result<(), &'static str> {
+            Ok(())
+        });
+        self_test!(fn check_flash() -> Result<(), &'static str> {
+            Err("flash id mismatch")
+        });
+
+        let mut report = Vec::new();
+        run_self_tests(|name, result| report.push((String::from(name), result.map_err(String::from))));
+        report.sort();
+        assert_eq!(
+            report,
+            [
+                (String::from("check_flash"), Err(String::from("flash id mismatch"))),
+                (String::from("check_ram"), Ok(())),
+            ]
+        );
+        assert_eq!(check_ram(), Ok(()));
+    }
+
+    /// Tests a full layout assertion for a `#[repr(C)]` struct.
+    #[test]
+    fn test_assert_layout() {
+        #[repr(C)]
+        struct Header {
+            kind: u8,
+            length: u16,
+            checksum: u32,
         }
 
-        unsafe {
-            let buf = &mut *self.buf.get();
-            if buf.len() < pos + bytes.len() {
-                let new_len = (pos + bytes.len()).next_power_of_two();
-                buf.resize(new_len, 0);
-            }
-            buf[pos..pos + bytes.len()].copy_from_slice(bytes);
-            *self.pos.get() = pos + bytes.len();
+        assert_layout! {
+            struct Header {
+                kind: u8 @ 0,
+                length: u16 @ 2,
+                checksum: u32 @ 4,
+            },
+            size = 8,
+            align = 4
         }
-        Ok(())
+
+        let header = Header { kind: 1, length: 2, checksum: 3 };
+        assert_eq!(header.kind as u32 + header.length as u32 + header.checksum, 6);
     }
-}
 
-/// A helper function to write formatted arguments to a buffer through a shared reference.
-#[doc(hidden)]
-pub fn write(buffer: &Buffer, args: core::fmt::Arguments) -> core::fmt::Result {
-    struct WriteAdapter<'a>(&'a Buffer);
+    /// Tests rounding to power-of-two alignments.
+    #[test]
+    fn test_align_up_down() {
+        assert_eq!(align_up(0, 8), 0);
+        assert_eq!(align_up(16, 8), 16);
+        assert_eq!(align_up(17, 8), 24);
+        assert_eq!(align_up(5, 1), 5);
 
-    impl<'a> core::fmt::Write for WriteAdapter<'a> {
-        fn write_str(&mut self, s: &str) -> core::fmt::Result {
-            let bytes = s.as_bytes();
-            let pos = unsafe { *self.0.pos.get() };
-            
-            if !self.0.has_capacity(bytes.len()) && !self.0.try_grow(bytes.len()) {
-                return Err(core::fmt::Error);
-            }
+        assert_eq!(align_down(0, 8), 0);
+        assert_eq!(align_down(16, 8), 16);
+        assert_eq!(align_down(23, 8), 16);
+        assert_eq!(align_down(5, 1), 5);
+    }
 
-            unsafe {
-                let buf = &mut *self.0.buf.get();
-                if buf.len() < pos + bytes.len() {
-                    let new_len = (pos + bytes.len()).next_power_of_two();
-                    buf.resize(new_len, 0);
-                }
-                buf[pos..pos + bytes.len()].copy_from_slice(bytes);
-                *self.0.pos.get() = pos + bytes.len();
-            }
-            Ok(())
-        }
+    /// Tests that `align_up` panics instead of wrapping past `usize::MAX`.
+    #[test]
+    #[should_panic(expected = "align_up overflowed usize")]
+    fn test_align_up_overflow() {
+        align_up(usize::MAX - 2, 8);
     }
-    core::fmt::write(&mut WriteAdapter(buffer), args)
-}
 
-/// A macro for formatting text in a no_std environment.
-/// 
-/// This macro provides string formatting capabilities similar to the standard library's
-/// `format!` macro, but designed specifically for no_std environments. It uses a dynamic
-/// buffer for formatting and is thread-safe.
-/// 
-/// # Features
-/// - Thread-safe formatting using static buffers
-/// - Compile-time format string validation
-/// - Dynamic buffer growth up to 1MB
-/// - Efficient memory usage with small initial buffer
-/// - Error handling for buffer overflow
-/// 
-/// # Examples
-/// 
-/// Basic string formatting:
-/// ```rust
-/// use noir_macros_core::format;
-/// 
-/// let name = "World";
-/// let greeting = format!("Hello, {}!", name);
-/// assert_eq!(greeting, "Hello, World!");
-/// ```
-/// 
-/// Multiple arguments and different types:
-/// ```rust
-/// use noir_macros_core::format;
-/// 
-/// let count = 42;
-/// let value = 3.14;
-/// let result = format!("Count: {}, Value: {:.2}", count, value);
-/// assert_eq!(result, "Count: 42, Value: 3.14");
-/// ```
-/// 
-/// # Buffer Size
-/// - Initial buffer size: 8KB (DEFAULT_BUFFER_SIZE)
-/// - Maximum buffer size: 1MB (MAX_BUFFER_SIZE)
-/// - Buffer grows dynamically as needed
-/// - Returns error if formatted string would exceed maximum size
-#[macro_export]
-macro_rules! format {
-    ($($arg:tt)*) => {{
-        // Validate format string at compile time
-        let _ = {
-            #[allow(unused_imports)]
-            use core::fmt::Write;
-            let mut _pw = $crate::PrintWrapper {};
-            core::fmt::write(&mut _pw, core::format_args!($($arg)*))
-        };
-        
-        static BUFFER: $crate::StaticCell<$crate::Buffer> = $crate::StaticCell::new();
-        
-        // Initialize buffer if not already initialized
-        if BUFFER.try_init($crate::Buffer::new()) {
-            // First time initialization
+    /// Tests that concurrent `println!` calls never interleave their output.
+    #[test]
+    fn test_println_no_interleaving() {
+        extern crate std;
+
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    for line in 0..25 {
+                        println!("interleave-check thread={} line={}", thread, line);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
         }
-        
-        // Get reference to buffer and format string
-        if let Some(buffer) = BUFFER.get() {
-            unsafe {
-                *buffer.pos.get() = 0;
-                let _ = $crate::write(buffer, core::format_args!($($arg)*));
-                core::str::from_utf8_unchecked(&(*buffer.buf.get())[..*buffer.pos.get()])
-            }
-        } else {
-            "" // Return empty string if buffer not available
+
+        let captured = PRINT_CAPTURE.lock().clone();
+        let output = core::str::from_utf8(&captured).unwrap();
+        let lines: Vec<&str> = output
+            .lines()
+            .filter(|line| line.contains("interleave-check"))
+            .collect();
+        assert_eq!(lines.len(), 100);
+        for line in lines {
+            let rest = line.strip_prefix("interleave-check thread=").unwrap();
+            let (thread, line) = rest.split_once(" line=").unwrap();
+            assert!(thread.parse::<u32>().unwrap() < 4);
+            assert!(line.parse::<u32>().unwrap() < 25);
         }
-    }};
-}
+    }
 
-/// A macro for debug formatting in no_std environments.
-///
-/// This macro works similarly to the standard library's `dbg!` macro but is
-/// designed for no_std environments. It prints the expression and its value,
-/// and returns the value.
-///
-/// # Examples
-///
-/// ```rust
-/// use noir_macros_core::debug;
-///
-/// let x = 42;
-/// let y = debug!(x + 1); // prints "[DEBUG] x + 1 = 43"
-/// assert_eq!(y, 43);
-/// ```
-#[macro_export]
-macro_rules! debug {
-    ($val:expr) => {{
-        match $val {
-            tmp => {
-                $crate::println!("[DEBUG] {} = {:?}", stringify!($val), &tmp);
-                tmp
-            }
+    /// Tests that a contended `SpinLock` calls the registered yield hook.
+    #[test]
+    fn test_yield_hook() {
+        static YIELDS: AtomicUsize = AtomicUsize::new(0);
+        fn count_yield() {
+            YIELDS.fetch_add(1, Ordering::SeqCst);
+            core::hint::spin_loop();
         }
-    }};
-    ($($val:expr),+ $(,)?) => {
-        ($($crate::debug!($val)),+,)
-    };
-}
 
-/// A macro for defining bit flags in a type-safe way.
-///
-/// This macro creates a type-safe bit flag enum that can be combined
-/// using bitwise operations.
-///
-/// # What are Bitflags?
-/// Bitflags are a programming pattern where individual bits in an integer are used
-/// to represent boolean flags. This is memory-efficient and allows for fast operations.
-///
-/// # Why Use Bitflags?
-/// - Memory Efficient: Multiple flags in a single integer
-/// - Fast Operations: Bitwise operations are very fast
-/// - Type Safe: Rust's type system prevents invalid combinations
-///
-/// # How Bitflags Work
-/// Each flag is a power of 2 (1, 2, 4, 8, 16, etc.) so that each bit represents
-/// a unique flag:
-/// ```text
-/// Bit Position:  7  6  5  4  3  2  1  0
-/// Binary:        0  0  0  0  0  1  0  1
-///                            ↑  ↑  ↑  ↑
-///                            8  4  2  1
-/// ```
-///
-/// # Example Usage
-/// ```rust
-/// use noir_macros_core::bitflags;
-/// bitflags! {
-///     /// File permissions in a Unix-like system
-///     pub struct Permissions: u8 {
-///         const READ    = 0b0000_0100;  // 4 in decimal
-///         const WRITE   = 0b0000_0010;  // 2 in decimal
-///         const EXECUTE = 0b0000_0001;  // 1 in decimal
-///     }
-/// }
-///
-/// // Combine permissions using bitwise OR (|)
-/// let read_write = Permissions::READ | Permissions::WRITE;
-///
-/// // Check permissions using contains()
-/// assert!(read_write.contains(Permissions::READ));
-/// assert!(!read_write.contains(Permissions::EXECUTE));
-/// ```
-///
-/// # Common Operations
-/// - `|` (OR): Combine flags
-/// - `&` (AND): Check if flags are present
-/// - `^` (XOR): Toggle flags
-/// - `!` (NOT): Invert flags
-///
-/// # Best Practices
-/// 1. Use descriptive names for your flags
-/// 2. Document the purpose of each flag
-/// 3. Group related flags together
-/// 4. Consider using a larger integer type (u32, u64) if you need many flags
-#[macro_export]
-macro_rules! bitflags {
-    (
-        $(#[$outer:meta])*
-        $vis:vis struct $name:ident: $type:ty {
-            $(
-                $(#[$inner:meta])*
-                const $flag:ident = $value:expr;
-            )*
+        assert!(set_yield_hook(count_yield));
+        assert!(!set_yield_hook(count_yield));
+
+        static LOCK: SpinLock<u32> = SpinLock::new(0);
+        let guard = LOCK.lock();
+        let before = YIELDS.load(Ordering::SeqCst);
+        let waiter = std::thread::spawn(|| *LOCK.lock() += 1);
+        while YIELDS.load(Ordering::SeqCst) == before {
+            std::thread::yield_now();
         }
-    ) => {
-        $(#[$outer])*
-        #[derive(Copy, Clone, PartialEq, Eq)]
-        #[repr(transparent)]
-        $vis struct $name($type);
+        drop(guard);
+        waiter.join().unwrap();
+        assert_eq!(*LOCK.lock(), 1);
+    }
 
-        impl core::fmt::Debug for $name {
-            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                f.debug_struct(stringify!($name))
-                    .field("bits", &format!("{:#b}", self.0))
-                    .finish()
-            }
+    /// Tests exhausting a `Pool` and reusing released slots.
+    #[test]
+    fn test_pool() {
+        let pool: Pool<u32, 3> = Pool::new();
+
+        let mut guards: Vec<_> = (0..3).map(|_| pool.acquire().unwrap()).collect();
+        for (value, guard) in guards.iter_mut().enumerate() {
+            **guard = value as u32 + 10;
         }
+        assert_eq!(pool.in_use(), 3);
+        assert!(pool.acquire().is_none());
 
-        impl $name {
-            $(
-                $(#[$inner])*
-                $vis const $flag: Self = Self($value);
-            )*
+        // releasing the middle slot makes exactly that slot available again
+        guards.remove(1);
+        assert_eq!(pool.in_use(), 2);
+        let reused = pool.acquire().unwrap();
+        assert_eq!(*reused, 11);
+        assert!(pool.acquire().is_none());
 
-            /// Returns an empty set of flags.
-            #[inline]
-            pub const fn empty() -> Self {
-                Self(0)
-            }
+        drop(reused);
+        drop(guards);
+        assert_eq!(pool.in_use(), 0);
+    }
 
-            /// Returns true if no flags are set.
-            #[inline]
-            pub const fn is_empty(self) -> bool {
-                self.0 == 0
-            }
+    /// Tests that a reused `SlotMap` slot rejects handles from before its removal.
+    #[test]
+    fn test_slot_map_generations() {
+        let map: SlotMap<alloc::string::String, 2> = SlotMap::new();
 
-            /// Returns true if all flags in other are set in self.
-            #[inline]
-            pub const fn contains(self, other: Self) -> bool {
-                (self.0 & other.0) == other.0
-            }
+        let first = map.insert("first".into()).unwrap();
+        let second = map.insert("second".into()).unwrap();
+        assert!(map.insert("third".into()).is_none());
+        assert_eq!(map.len(), 2);
 
-            /// Returns the raw bits of the flags.
-            #[inline]
-            pub const fn bits(self) -> $type {
-                self.0
-            }
+        assert_eq!(map.remove(first).as_deref(), Some("first"));
+        assert!(map.remove(first).is_none());
+
+        // the freed slot is reused, but the stale handle stays invalid
+        let reused = map.insert("reused".into()).unwrap();
+        assert_ne!(reused, first);
+        assert!(map.get(first).is_none());
+        assert!(map.remove(first).is_none());
+        assert_eq!(&*map.get(reused).unwrap(), "reused");
+        assert_eq!(&*map.get(second).unwrap(), "second");
+    }
+
+    /// Tests that no thread passes a `Barrier` before all have arrived.
+    #[test]
+    fn test_barrier() {
+        extern crate std;
+
+        const THREADS: usize = 4;
+        static BARRIER: Barrier<THREADS> = Barrier::new();
+        static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for round in 1..=3 {
+                        ARRIVED.fetch_add(1, Ordering::SeqCst);
+                        BARRIER.wait();
+                        assert!(ARRIVED.load(Ordering::SeqCst) >= round * THREADS);
+                        BARRIER.wait();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(ARRIVED.load(Ordering::SeqCst), 3 * THREADS);
+    }
+
+    /// Tests module-level filtering of `log!` against the global level.
+    #[test]
+    fn test_log_module_levels() {
+        set_log_level(Level::Warn);
+        assert!(set_module_level("driver::i2c", Level::Debug));
+        assert!(set_module_level("driver::i2c::eeprom", Level::Error));
+        assert!(set_module_level("tests", Level::Info));
+
+        assert!(log_enabled(Level::Debug, "fw::driver::i2c"));
+        assert!(log_enabled(Level::Debug, "driver::i2c::bus"));
+        assert!(!log_enabled(Level::Trace, "fw::driver::i2c"));
+        assert!(!log_enabled(Level::Warn, "fw::driver::i2c::eeprom"));
+        assert!(!log_enabled(Level::Debug, "fw::driver::i2c2"));
+        // no module level: the global level applies
+        assert!(log_enabled(Level::Warn, "fw::driver::spi"));
+        assert!(!log_enabled(Level::Info, "fw::driver::spi"));
+
+        // reading levels takes no lock, e.g. from an interrupt during an update
+        {
+            let _updating = MODULE_LEVELS_WRITE.lock();
+            assert!(log_enabled(Level::Debug, "fw::driver::i2c"));
         }
 
-        impl core::ops::BitOr for $name {
-            type Output = Self;
-            #[inline]
-            fn bitor(self, rhs: Self) -> Self {
-                Self(self.0 | rhs.0)
+        // updating a module keeps its single entry
+        assert!(set_module_level("tests", Level::Info));
+        log!(Level::Info, "log-module-info {}", 1);
+        log!(Level::Debug, "log-module-debug {}", 2);
+        let captured = PRINT_CAPTURE.lock().clone();
+        let output = core::str::from_utf8(&captured).unwrap();
+        assert!(output.contains("[Info] log-module-info 1\n"));
+        assert!(!output.contains("log-module-debug"));
+    }
+
+    /// Tests the column alignment of `print_table!`.
+    #[test]
+    fn test_print_table() {
+        print_table!([
+            ["table-name", "Value", "Unit"],
+            ["clk", "48", "MHz"],
+            ["vcore", "1.25", "V"],
+        ]);
+
+        let captured = PRINT_CAPTURE.lock().clone();
+        let output = core::str::from_utf8(&captured).unwrap();
+        assert!(output.contains(concat!(
+            "table-name  Value  Unit\n",
+            "clk         48     MHz\n",
+            "vcore       1.25   V\n",
+        )));
+    }
+
+    /// Tests a `Debug` impl that prints while being printed by `println!`.
+    #[test]
+    fn test_nested_print() {
+        struct Noisy;
+
+        impl core::fmt::Debug for Noisy {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                print!("nested-inner;");
+                f.write_str("nested-value")
             }
         }
 
-        impl core::ops::BitAnd for $name {
-            type Output = Self;
-            #[inline]
-            fn bitand(self, rhs: Self) -> Self {
-                Self(self.0 & rhs.0)
-            }
+        let written = print_len!("nested-outer {:?} {:?}\n", Noisy, Noisy);
+        assert_eq!(written, "nested-outer nested-value nested-value\n".len());
+
+        let captured = PRINT_CAPTURE.lock().clone();
+        let output = core::str::from_utf8(&captured).unwrap();
+        assert!(output.contains("nested-inner;nested-inner;nested-outer nested-value nested-value\n"));
+    }
+
+    mod vec_without_imports {
+        /// Tests `vec!` in a module that imports nothing from `alloc`.
+        #[test]
+        fn test_vec_macro() {
+            let empty: crate::Vec<u8> = crate::vec![];
+            assert!(empty.is_empty());
+            assert_eq!(crate::vec![7u16; 3], [7, 7, 7]);
+            assert_eq!(crate::vec![1, 2, 3,], [1, 2, 3]);
         }
+    }
 
-        impl core::ops::BitXor for $name {
-            type Output = Self;
-            #[inline]
-            fn bitxor(self, rhs: Self) -> Self {
-                Self(self.0 ^ rhs.0)
-            }
+    /// Tests that `AllocStats` live bytes return to baseline after freeing.
+    #[cfg(feature = "alloc-stats")]
+    #[test]
+    fn test_alloc_stats() {
+        use core::alloc::{GlobalAlloc, Layout};
+
+        let allocator = AllocStats::new(std::alloc::System);
+        let small = Layout::from_size_align(24, 8).unwrap();
+        let large = Layout::from_size_align(256, 16).unwrap();
+
+        unsafe {
+            let a = allocator.alloc(small);
+            let b = allocator.alloc_zeroed(large);
+            assert_eq!(allocator.stats(), (280, 280, 2));
+
+            allocator.dealloc(a, small);
+            let b = allocator.realloc(b, large, 512);
+            assert_eq!(allocator.stats(), (512, 512, 3));
+
+            allocator.dealloc(b, Layout::from_size_align(512, 16).unwrap());
         }
-    };
-}
+        assert_eq!(allocator.stats(), (0, 512, 3));
+    }
 
-#[cfg(test)]
-mod tests {
-    //! Test module for noir_macros_core functionality.
-    //! 
-    //! This module contains comprehensive tests for all public APIs
-    //! and ensures thread-safety, memory safety, and correct behavior
-    //! of the static cell and assertion macros.
-    
-    use super::*;
-    use core::fmt::Write;
+    /// Tests that `unwrap!` passes `Some` and `Ok` values through.
+    #[test]
+    fn test_unwrap_values() {
+        assert_eq!(unwrap!(Some(3u8)), 3);
+        assert_eq!(unwrap!(Ok::<_, ()>("ready")), "ready");
+        assert_eq!(unwrap!("7".parse::<u32>()) + 1, 8);
+    }
 
-    /// Tests basic static cell initialization and access.
-    /// 
-    /// # What This Test Teaches
-    /// - How to properly initialize a StaticCell
-    /// - Thread-safe access patterns
-    /// - Common initialization scenarios
-    /// 
-    /// # Key Concepts
-    /// 1. One-time initialization
-    /// 2. Thread safety
-    /// 3. Error handling
+    /// Tests the location in the panic of `unwrap!` on `None`.
     #[test]
-    fn test_static_cell() {
-        let cell = StaticCell::new();
-        assert!(cell.try_init(42));
-        assert_eq!(cell.get(), Some(&42));
-        assert!(!cell.try_init(24));
-        assert_eq!(cell.get(), Some(&42));
+    #[should_panic(expected = "unwrap! failed at src/lib.rs")]
+    fn test_unwrap_none() {
+        let missing: Option<u8> = None;
+        unwrap!(missing);
+    }
+
+    /// Tests the error in the panic of `unwrap!` on `Err`.
+    #[test]
+    #[should_panic(expected = "Err(\"no ack\")")]
+    fn test_unwrap_err() {
+        let nack: Result<u8, &str> = Err("no ack");
+        unwrap!(nack);
     }
 
     /// Tests the PrintWrapper implementation.
@@ -1134,4 +8095,508 @@ mod tests {
         assert_eq!(Flags::C.bits(), 0b0100);
         assert_eq!(Flags::D.bits(), 0b1000);
     }
+
+    /// Tests iteration over the declared flags of a bitflags value.
+    #[test]
+    fn test_bitflags_iter() {
+        bitflags! {
+            struct Flags: u16 {
+                const A = 0b0001;
+                const B = 0b0010;
+                const C = 0b0100;
+            }
+        }
+
+        let set = Flags::A | Flags::C;
+        let mut iter = set.iter();
+        assert_eq!(iter.len(), set.count());
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next(), Some(Flags::A));
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next(), Some(Flags::C));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(Flags::empty().iter().len(), 0);
+        assert_eq!((Flags::A | Flags::B | Flags::C).count(), 3);
+    }
+
+    /// Tests folding a sequence of flag sets with `Sum`.
+    #[test]
+    fn test_bitflags_sum() {
+        bitflags! {
+            struct Flags: u8 {
+                const A = 0b001;
+                const B = 0b010;
+                const C = 0b100;
+            }
+        }
+
+        let sets = alloc::vec![Flags::A, Flags::B | Flags::C, Flags::A];
+        assert_eq!(sets.iter().sum::<Flags>(), Flags::A | Flags::B | Flags::C);
+        assert_eq!(sets.into_iter().sum::<Flags>(), Flags::A | Flags::B | Flags::C);
+        assert_eq!(core::iter::empty::<Flags>().sum::<Flags>(), Flags::empty());
+    }
+
+    /// Tests generic code constrained on the `Bitflags` trait.
+    #[test]
+    fn test_bitflags_generic() {
+        bitflags_generic! {
+            struct Small: u8 {
+                const A = 1 << 0;
+                const B = 1 << 7;
+            }
+        }
+
+        bitflags! {
+            struct Wide: u64 {
+                const LOW = 1 << 0;
+                const HIGH = 1 << 40;
+            }
+        }
+
+        fn fits<F: Bitflags>(required: u32) -> bool {
+            F::WIDTH >= required
+        }
+
+        fn set_or_empty<F: Bitflags>(set: F, flag: F) -> F {
+            if set.contains(flag) { flag } else { F::empty() }
+        }
+
+        assert_eq!(Small::WIDTH, 8);
+        assert_eq!(Wide::WIDTH, 64);
+        assert!(fits::<Small>(8));
+        assert!(!fits::<Small>(12));
+        assert!(fits::<Wide>(41));
+
+        assert_eq!(set_or_empty(Small::A | Small::B, Small::B), Small::B);
+        assert_eq!(set_or_empty(Wide::LOW, Wide::HIGH), Wide::empty());
+        assert_eq!(Bitflags::bits(Wide::HIGH), 1 << 40);
+
+        // `WIDTH` is only the trait constant, so it is free as a flag name
+        bitflags! {
+            struct Bus: u8 {
+                const WIDTH = 1 << 0;
+            }
+        }
+        assert_eq!(Bus::WIDTH.bits(), 1);
+        assert_eq!(<Bus as Bitflags>::WIDTH, 8);
+    }
+
+    /// Tests a function generic over two different `bitflags!` types.
+    #[test]
+    fn test_bitflags_trait() {
+        bitflags! {
+            struct Color: u8 {
+                const RED = 0b001;
+                const GREEN = 0b010;
+                const BLUE = 0b100;
+            }
+        }
+
+        bitflags! {
+            struct Mode: u32 {
+                const READ = 1 << 0;
+                const WRITE = 1 << 16;
+            }
+        }
+
+        fn missing<F: Bitflags + PartialEq>(set: F) -> bool {
+            !set.contains(F::all()) && set != F::empty()
+        }
+
+        assert_eq!(Color::all(), Color::RED | Color::GREEN | Color::BLUE);
+        assert_eq!(<Mode as Bitflags>::all().bits(), (1 << 16) | 1);
+        assert!(missing(Color::RED));
+        assert!(!missing(Color::all()));
+        assert!(!missing(Color::empty()));
+        assert!(missing(Mode::WRITE));
+        assert!(!missing(Mode::READ | Mode::WRITE));
+    }
+
+    /// Tests strict and forward-compatible handling of undeclared bits.
+    #[test]
+    fn test_bitflags_retain_unknown() {
+        bitflags! {
+            struct Strict: u8 {
+                const A = 0b01;
+                const B = 0b10;
+            }
+        }
+
+        bitflags! {
+            struct Lenient: u8 {
+                const A = 0b01;
+                const B = 0b10;
+            } with retain_unknown
+        }
+
+        assert_eq!(Strict::from_bits(0b11), Some(Strict::A | Strict::B));
+        assert_eq!(Strict::from_bits(0b101), None);
+        assert_eq!(Strict::from_bits_retain(0b101).known(), Strict::A);
+
+        // an unknown bit survives decoding, combination and re-encoding
+        let decoded = Lenient::from_bits(0b1001).unwrap();
+        let updated = decoded | Lenient::B;
+        assert_eq!(updated.bits(), 0b1011);
+        assert_eq!(updated.known_bits(), 0b0011);
+        assert_eq!(updated.known(), Lenient::A | Lenient::B);
+        assert_eq!(Lenient::from_bits(updated.bits()), Some(updated));
+    }
+
+    /// Tests `is_disjoint` in const and runtime contexts.
+    #[test]
+    fn test_bitflags_is_disjoint() {
+        bitflags! {
+            struct Access: u8 {
+                const READ = 0b0001;
+                const READ_AHEAD = 0b0010;
+                const WRITE = 0b0100;
+                const WRITE_BACK = 0b1000;
+            }
+        }
+
+        const READS: Access = Access(Access::READ.0 | Access::READ_AHEAD.0);
+        const WRITES: Access = Access(Access::WRITE.0 | Access::WRITE_BACK.0);
+        const _: () = assert!(READS.is_disjoint(WRITES));
+
+        assert!(Access::empty().is_disjoint(Access::empty()));
+        assert!(!(Access::READ | Access::WRITE).is_disjoint(WRITES));
+        assert!(Access::READ_AHEAD.is_disjoint(Access::READ));
+    }
+
+    /// Tests the `with default` option of `bitflags!`.
+    #[test]
+    fn test_bitflags_default() {
+        bitflags! {
+            struct Perms: u8 {
+                const READ = 0b001;
+                const WRITE = 0b010;
+                const EXECUTE = 0b100;
+            } with default READ | WRITE
+        }
+
+        bitflags! {
+            struct Mode: u8 {
+                const FAST = 0b01;
+                const SAFE = 0b10;
+            } with retain_unknown with default SAFE
+        }
+
+        #[derive(Default)]
+        struct Config {
+            perms: Perms,
+            mode: Mode,
+        }
+
+        let config = Config::default();
+        assert_eq!(config.perms, Perms::READ | Perms::WRITE);
+        assert_eq!(config.mode, Mode::SAFE);
+        assert_eq!(Mode::from_bits(0b110).map(Mode::bits), Some(0b110));
+
+        const PERMS: Perms = Perms::default_flags();
+        assert_eq!(PERMS, Perms::default());
+
+        // the const form is a method, so `DEFAULT` is free as a flag name
+        bitflags! {
+            struct Boot: u8 {
+                const DEFAULT = 0b01;
+                const RECOVERY = 0b10;
+            } with default RECOVERY
+        }
+        assert_eq!(Boot::default(), Boot::RECOVERY);
+        assert_eq!(Boot::DEFAULT.bits(), 0b01);
+    }
+
+    /// Tests named group masks declared with `group`.
+    #[test]
+    fn test_bitflags_groups() {
+        bitflags! {
+            struct Access: u8 {
+                const READ = 1 << 0;
+                const WRITE = 1 << 1;
+                const EXECUTE = 1 << 2;
+                const STICKY = 1 << 6;
+                group ALL_PERMS = READ | WRITE | EXECUTE;
+                group READ_WRITE = READ | WRITE;
+            }
+        }
+
+        assert_eq!(Access::ALL_PERMS.bits(), 0b111);
+        assert!(Access::ALL_PERMS.contains(Access::READ_WRITE));
+        assert!(Access::ALL_PERMS.contains(Access::EXECUTE));
+        assert!(!(Access::READ | Access::STICKY).contains(Access::READ_WRITE));
+        assert!((Access::READ_WRITE | Access::STICKY).contains(Access::READ_WRITE));
+
+        // groups are not declared flags
+        assert_eq!(Access::ALL_PERMS.count(), 3);
+        assert_eq!(Access::all().to_bools().len(), 4);
+        assert_eq!(alloc::format!("{}", Access::READ_WRITE), "READ|WRITE");
+    }
+
+    /// Tests round-tripping flags through a bool array.
+    #[test]
+    fn test_bitflags_bools() {
+        bitflags! {
+            struct Options: u8 {
+                const BOLD = 1 << 0;
+                const ITALIC = 1 << 3;
+                const UNDERLINE = 1 << 5;
+            }
+        }
+
+        let options = Options::BOLD | Options::UNDERLINE;
+        assert_eq!(options.to_bools(), [true, false, true]);
+        assert_eq!(Options::from_bools([true, false, true]), options);
+
+        let bools: [bool; 3] = Options::ITALIC.into();
+        assert_eq!(bools, [false, true, false]);
+        assert_eq!(Options::from(bools), Options::ITALIC);
+        assert_eq!(Options::from_bools([false; 3]), Options::empty());
+    }
+
+    /// Tests that `checked_insert` rejects undeclared bits.
+    #[test]
+    fn test_bitflags_checked_insert() {
+        bitflags! {
+            struct Irq: u8 {
+                const TIMER = 0b0001;
+                const UART = 0b0100;
+            }
+        }
+
+        let mut enabled = Irq::empty();
+        assert_eq!(enabled.checked_insert(0b0001), Ok(()));
+        assert_eq!(enabled.checked_insert(0b0101), Ok(()));
+        assert_eq!(enabled, Irq::TIMER | Irq::UART);
+
+        let mut enabled = Irq::TIMER;
+        assert_eq!(enabled.checked_insert(0b1110), Err(0b1010));
+        assert_eq!(enabled, Irq::TIMER);
+        assert_eq!(enabled.checked_insert(0), Ok(()));
+    }
+
+    /// Tests rebuilding flags from raw bits with `from_bits` and `from_bits_truncate`.
+    #[test]
+    fn test_bitflags_from_bits_truncate() {
+        bitflags! {
+            struct Mode: u8 {
+                const READ = 0b001;
+                const WRITE = 0b010;
+                const EXECUTE = 0b100;
+            }
+        }
+
+        for flags in [Mode::empty(), Mode::READ, Mode::WRITE | Mode::EXECUTE, Mode::all()] {
+            assert_eq!(Mode::from_bits(flags.bits()), Some(flags));
+            assert_eq!(Mode::from_bits_truncate(flags.bits()), flags);
+        }
+
+        assert_eq!(Mode::from_bits(0b1000_0011), None);
+        assert_eq!(Mode::from_bits_truncate(0b1000_0011), Mode::READ | Mode::WRITE);
+        assert_eq!(Mode::from_bits_truncate(0xf8), Mode::empty());
+    }
+
+    /// Tests a masked read-modify-write through `bits_mut`.
+    #[test]
+    fn test_bitflags_bits_mut() {
+        bitflags! {
+            struct Control: u32 {
+                const ENABLE = 1 << 0;
+                const IRQ = 1 << 1;
+            }
+        }
+
+        const PRESCALER_MASK: u32 = 0xf << 4;
+
+        // read: the register holds ENABLE and a prescaler field of 3
+        let mut control = Control::from_bits_retain(0x31);
+        // modify: set the prescaler to 9 and enable the interrupt
+        *control.bits_mut() = (control.bits() & !PRESCALER_MASK) | (9 << 4);
+        *control.bits_mut() |= Control::IRQ.bits();
+
+        // write back
+        assert_eq!(control.bits(), 0x93);
+        assert!(control.contains(Control::ENABLE | Control::IRQ));
+        assert_eq!(control.known(), Control::all());
+    }
+
+    /// Tests that `all()` covers exactly the declared flags.
+    #[test]
+    fn test_bitflags_all() {
+        bitflags! {
+            struct Status: u16 {
+                const READY = 1 << 0;
+                const BUSY = 1 << 4;
+                const FAULT = 1 << 9;
+                const OVERRUN = 1 << 15;
+            }
+        }
+
+        const ALL: Status = Status::all();
+        assert_eq!(ALL.bits(), (1 << 0) | (1 << 4) | (1 << 9) | (1 << 15));
+        for flag in [Status::READY, Status::BUSY, Status::FAULT, Status::OVERRUN] {
+            assert!(ALL.contains(flag));
+        }
+        assert_eq!(ALL.count(), 4);
+        assert_eq!(Status::from_bits_retain(0xffff).known(), ALL);
+    }
+
+    /// Tests overlap checks with `intersects` against `contains`.
+    #[test]
+    fn test_bitflags_intersects() {
+        bitflags! {
+            struct Perms: u8 {
+                const READ = 1 << 0;
+                const WRITE = 1 << 1;
+                const APPEND = 1 << 2;
+                const EXECUTE = 1 << 3;
+            }
+        }
+
+        let writes = Perms::WRITE | Perms::APPEND;
+        let granted = Perms::READ | Perms::APPEND;
+        assert!(granted.intersects(writes));
+        assert!(!granted.contains(writes));
+
+        assert!(!Perms::EXECUTE.intersects(writes));
+        assert!(!granted.intersects(Perms::empty()));
+        assert_eq!(granted.intersects(Perms::EXECUTE), !granted.is_disjoint(Perms::EXECUTE));
+    }
+
+    /// Tests building flag sets with `insert`, `remove` and `toggle`.
+    #[test]
+    fn test_bitflags_insert_remove_toggle() {
+        bitflags! {
+            struct Pins: u8 {
+                const CS = 1 << 0;
+                const CLK = 1 << 1;
+                const MOSI = 1 << 2;
+            }
+        }
+
+        const IDLE: Pins = Pins::empty().insert(Pins::CS);
+        assert_eq!(IDLE.insert(Pins::CS), Pins::CS);
+        assert_eq!(IDLE.insert(Pins::CLK), Pins::CS | Pins::CLK);
+
+        assert_eq!(IDLE.remove(Pins::MOSI), IDLE);
+        assert_eq!(Pins::all().remove(Pins::CLK), Pins::CS | Pins::MOSI);
+
+        assert_eq!(IDLE.toggle(Pins::CLK), Pins::CS | Pins::CLK);
+        assert_eq!(IDLE.toggle(Pins::CS), Pins::empty());
+        assert_eq!(IDLE.toggle(Pins::MOSI).toggle(Pins::MOSI), IDLE);
+    }
+
+    /// Tests the const set operations in a const context.
+    #[test]
+    fn test_bitflags_set_algebra() {
+        bitflags! {
+            struct Events: u8 {
+                const RX = 1 << 0;
+                const TX = 1 << 1;
+                const ERROR = 1 << 2;
+                const WAKE = 1 << 3;
+            }
+        }
+
+        const DATA: Events = Events::RX.union(Events::TX);
+        const TOGGLED: Events = DATA.symmetric_difference(Events::TX.union(Events::ERROR));
+        const QUIET: Events = DATA.complement();
+        const RX_ONLY: Events = DATA.difference(Events::TX);
+        const SHARED: Events = DATA.intersection(TOGGLED);
+
+        assert_eq!(TOGGLED, Events::RX | Events::ERROR);
+        assert_eq!(TOGGLED, DATA ^ (Events::TX | Events::ERROR));
+        assert_eq!(DATA.symmetric_difference(DATA), Events::empty());
+        assert_eq!(QUIET, Events::ERROR | Events::WAKE);
+        assert_eq!(RX_ONLY, Events::RX);
+        assert_eq!(SHARED, Events::RX);
+        assert_eq!(Events::from_bits_retain(0xf0).complement(), Events::all());
+    }
+
+    /// Tests that `!` complements within the declared flags only.
+    #[test]
+    fn test_bitflags_not() {
+        bitflags! {
+            struct Flags: u8 {
+                const A = 1 << 0;
+                const B = 1 << 1;
+                const C = 1 << 2;
+            }
+        }
+
+        assert_eq!(!Flags::A, Flags::B | Flags::C);
+        assert_eq!((!Flags::A).bits(), 0b110);
+        assert_eq!(!Flags::all(), Flags::empty());
+        assert_eq!(!Flags::empty(), Flags::all());
+    }
+
+    /// Tests subset validation with `contains_only`.
+    #[test]
+    fn test_bitflags_contains_only() {
+        bitflags! {
+            struct Options: u8 {
+                const VERBOSE = 0b001;
+                const FORCE = 0b010;
+                const DRY_RUN = 0b100;
+            }
+        }
+
+        let allowed = Options::VERBOSE | Options::DRY_RUN;
+        assert!(Options::VERBOSE.contains_only(allowed));
+        assert!(allowed.contains_only(allowed));
+        assert!(Options::empty().contains_only(allowed));
+        assert!(!(Options::VERBOSE | Options::FORCE).contains_only(allowed));
+    }
+
+    /// Tests the `Display`/`parse` text round trip for bitflags.
+    #[test]
+    fn test_bitflags_text_round_trip() {
+        use alloc::string::ToString;
+
+        bitflags! {
+            struct Access: u8 {
+                const READ = 0b001;
+                const WRITE = 0b010;
+                const EXEC = 0b100;
+            }
+        }
+
+        assert_eq!((Access::READ | Access::WRITE).to_string(), "READ|WRITE");
+        assert_eq!(Access::empty().to_string(), "");
+        assert_eq!(Access(0b1001).to_string(), "READ|0x8");
+
+        for flags in [
+            Access::empty(),
+            Access::READ,
+            Access::READ | Access::EXEC,
+            Access::READ | Access::WRITE | Access::EXEC,
+            Access(0b1_0010),
+        ] {
+            assert_eq!(Access::parse(&flags.to_string()), Some(flags));
+        }
+
+        assert_eq!(Access::from_name("WRITE"), Some(Access::WRITE));
+        assert_eq!(Access::parse(" READ | EXEC "), Some(Access::READ | Access::EXEC));
+        assert_eq!(Access::parse("READ|BOGUS"), None);
+    }
+
+    /// Tests positional flag lookup for a sparse flag layout.
+    #[test]
+    fn test_bitflags_from_flag_index() {
+        bitflags! {
+            struct Sparse: u32 {
+                const LOW = 1 << 0;
+                const MID = 1 << 5;
+                const HIGH = 1 << 31;
+                const BOTH = (1 << 0) | (1 << 5);
+            }
+        }
+
+        assert_eq!(Sparse::from_flag_index(0), Some(Sparse::LOW));
+        assert_eq!(Sparse::from_flag_index(5), Some(Sparse::MID));
+        assert_eq!(Sparse::from_flag_index(31), Some(Sparse::HIGH));
+        assert_eq!(Sparse::from_flag_index(1), None);
+        assert_eq!(Sparse::from_flag_index(32), None);
+        assert_eq!(Sparse::BOTH.count(), 3);
+    }
 }